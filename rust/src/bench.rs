@@ -0,0 +1,16 @@
+use std::hint::black_box;
+use std::time::Instant;
+
+pub fn measure<T: PartialEq + std::fmt::Debug>(
+    label: &str,
+    iterations: u32,
+    mut f: impl FnMut() -> T,
+) -> T {
+    let result = f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        assert_eq!(black_box(f()), result);
+    }
+    println!("{label}: {:?} / iteration", start.elapsed() / iterations);
+    result
+}
@@ -1,22 +1,54 @@
-use std::fs;
-
+use crate::parse::{ErrorKind, ParseError, Position, Text};
 use crate::puzzle::AocPuzzle;
 
-fn get_puzzle() -> String {
-    fs::read_to_string("puzzle_1_1").expect("Should have been able to read the file")
+// Which characters count as digits
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Digits {
+    #[default]
+    Ascii,
+    // Any Unicode decimal digit (category Nd), e.g. '٣' or '３'
+    Unicode,
 }
 
-fn concat_two_digit_chars(c1: char, c2: char) -> u32 {
-    let mut str = String::new();
-    str.push(c1);
-    str.push(c2);
-    str.parse().unwrap()
+// First code point of each run of ten decimal digits
+const DECIMAL_ZEROS: [u32; 77] = [
+    0x30, 0x660, 0x6F0, 0x7C0, 0x966, 0x9E6, 0xA66, 0xAE6, 0xB66, 0xBE6, 0xC66, 0xCE6, 0xD66,
+    0xDE6, 0xE50, 0xED0, 0xF20, 0x1040, 0x1090, 0x17E0, 0x1810, 0x1946, 0x19D0, 0x1A80, 0x1A90,
+    0x1B50, 0x1BB0, 0x1C40, 0x1C50, 0xA620, 0xA8D0, 0xA900, 0xA9D0, 0xA9F0, 0xAA50, 0xABF0, 0xFF10,
+    0x104A0, 0x10D30, 0x10D40, 0x11066, 0x110F0, 0x11136, 0x111D0, 0x112F0, 0x11450, 0x114D0,
+    0x11650, 0x116C0, 0x116D0, 0x116DA, 0x11730, 0x118E0, 0x11950, 0x11BF0, 0x11C50, 0x11D50,
+    0x11DA0, 0x11DE0, 0x11F50, 0x16130, 0x16A60, 0x16AC0, 0x16B50, 0x16D70, 0x1CCF0, 0x1D7CE,
+    0x1D7D8, 0x1D7E2, 0x1D7EC, 0x1D7F6, 0x1E140, 0x1E2F0, 0x1E4F0, 0x1E5F1, 0x1E950, 0x1FBF0,
+];
+
+impl Digits {
+    pub fn from_name(name: &str) -> Option<Digits> {
+        match name {
+            "ascii" => Some(Digits::Ascii),
+            "unicode" => Some(Digits::Unicode),
+            _ => None,
+        }
+    }
+
+    fn value(self, c: char) -> Option<u32> {
+        match self {
+            Digits::Ascii => c.to_digit(10),
+            Digits::Unicode => {
+                let c = c as u32;
+                let i = DECIMAL_ZEROS
+                    .partition_point(|zero| *zero <= c)
+                    .checked_sub(1)?;
+                let value = c - DECIMAL_ZEROS[i];
+                (value < 10).then_some(value)
+            }
+        }
+    }
 }
 
-fn add_first_and_last_digit_of_line(s: &str) -> u32 {
-    let first = s.chars().find(|c| c.is_numeric());
-    let last = s.chars().rev().find(|c| c.is_numeric());
-    concat_two_digit_chars(first.unwrap(), last.unwrap())
+pub(crate) fn add_first_and_last_digit_of_line(s: &str, digits: Digits) -> Option<u32> {
+    let first = s.chars().find_map(|c| digits.value(c))?;
+    let last = s.chars().rev().find_map(|c| digits.value(c))?;
+    Some(first * 10 + last)
 }
 
 const ENGLISH_NUMBERS: [&str; 10] = [
@@ -28,16 +60,16 @@ fn get_english_number(s: &str) -> Option<u32> {
         .iter()
         .enumerate()
         .find(|(_, x)| s.contains(*x))
-        .and_then(|(i, _)| Some(i.try_into().unwrap()))
+        .map(|(i, _)| i.try_into().unwrap())
 }
 
-fn get_first_and_last_digit_with_english_words(s: &str) -> u32 {
+pub(crate) fn get_first_and_last_digit_with_english_words(s: &str, digits: Digits) -> Option<u32> {
     let mut buffer = String::new();
     let mut nums: Vec<u32> = vec![];
 
     for c in s.chars() {
         buffer.push(c);
-        if let Some(n) = c.to_digit(10) {
+        if let Some(n) = digits.value(c) {
             nums.push(n);
             buffer.clear();
             continue;
@@ -48,41 +80,116 @@ fn get_first_and_last_digit_with_english_words(s: &str) -> u32 {
             buffer.push(c);
         }
     }
-    let first = nums.first().unwrap();
-    let last = nums.last().unwrap();
-    first * 10 + last
+    let first = nums.first()?;
+    let last = nums.last()?;
+    Some(first * 10 + last)
 }
 
-fn add_first_and_last_digit_of_text_lines(s: &str, puzzle: AocPuzzle) -> u32 {
-    s.lines().fold(0, |a, b| {
-        a + match puzzle {
-            AocPuzzle::PartOne => add_first_and_last_digit_of_line(b),
-            AocPuzzle::PartTwo => get_first_and_last_digit_with_english_words(b),
-        }
+pub(crate) fn add_first_and_last_digit_of_text_lines(
+    s: &str,
+    puzzle: AocPuzzle,
+    digits: Digits,
+) -> Result<u64, ParseError> {
+    Text::new(s).lines().try_fold(0, |a, line| {
+        let value = match puzzle {
+            AocPuzzle::PartOne => add_first_and_last_digit_of_line(line.as_str(), digits),
+            AocPuzzle::PartTwo => {
+                get_first_and_last_digit_with_english_words(line.as_str(), digits)
+            }
+        };
+        Ok(a + u64::from(value.ok_or_else(|| line.expected("a digit"))?))
     })
 }
 
-pub fn day_1(puzzle: AocPuzzle) -> u32 {
-    let puzzle_file = get_puzzle();
-    let result = add_first_and_last_digit_of_text_lines(&puzzle_file, puzzle);
-    return result;
+const ONES: u64 = 0x0101_0101_0101_0101;
+const HIGHS: u64 = 0x8080_8080_8080_8080;
+
+// memchr, eight bytes at a time: a byte of `word ^ pattern` is zero where
+// `byte` is, and the lowest zero byte sets the lowest high bit below.
+fn find_byte(haystack: &[u8], byte: u8) -> Option<usize> {
+    let pattern = ONES * u64::from(byte);
+    let mut chunks = haystack.chunks_exact(8);
+    let mut offset = 0;
+    for chunk in &mut chunks {
+        let word = u64::from_le_bytes(chunk.try_into().unwrap()) ^ pattern;
+        let found = word.wrapping_sub(ONES) & !word & HIGHS;
+        if found != 0 {
+            return Some(offset + (found.trailing_zeros() / 8) as usize);
+        }
+        offset += 8;
+    }
+    chunks
+        .remainder()
+        .iter()
+        .position(|b| *b == byte)
+        .map(|i| offset + i)
+}
+
+// Part 1 with ASCII digits, without decoding chars or allocating: lines are
+// found with `find_byte`, then only scanned up to their first and last digit.
+pub(crate) fn sum_ascii_lines(input: &[u8]) -> Result<u64, ParseError> {
+    let mut sum = 0;
+    let mut rest = input;
+    let mut line_number = 1;
+    while !rest.is_empty() {
+        let end = find_byte(rest, b'\n').unwrap_or(rest.len());
+        let line = &rest[..end];
+        let (Some(first), Some(last)) = (
+            line.iter().find(|b| b.is_ascii_digit()),
+            line.iter().rev().find(|b| b.is_ascii_digit()),
+        ) else {
+            return Err(ParseError {
+                position: Position {
+                    line: line_number,
+                    column: 1,
+                },
+                kind: ErrorKind::Expected("a digit".to_string()),
+            });
+        };
+        sum += u64::from(first - b'0') * 10 + u64::from(last - b'0');
+        rest = rest.get(end + 1..).unwrap_or_default();
+        line_number += 1;
+    }
+    Ok(sum)
+}
+
+pub fn solve_with(input: &str, puzzle: AocPuzzle, digits: Digits) -> Result<u64, ParseError> {
+    match (puzzle, digits) {
+        (AocPuzzle::PartOne, Digits::Ascii) => sum_ascii_lines(input.as_bytes()),
+        _ => add_first_and_last_digit_of_text_lines(input, puzzle, digits),
+    }
+}
+
+pub fn solve(input: &str, puzzle: AocPuzzle) -> Result<u64, ParseError> {
+    solve_with(input, puzzle, Digits::Ascii)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{day1::add_first_and_last_digit_of_text_lines, AocPuzzle};
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use crate::bench::measure;
+    use crate::day1::{
+        add_first_and_last_digit_of_line, add_first_and_last_digit_of_text_lines, find_byte,
+        get_first_and_last_digit_with_english_words, solve, solve_with, sum_ascii_lines, Digits,
+        DECIMAL_ZEROS,
+    };
+    use crate::generate::{generate, Options};
+    use crate::puzzle::{self, normalize, SolveError};
+    use crate::rng::Rng;
+    use crate::AocPuzzle;
 
     #[test]
     fn test_puzzle1() {
         assert_eq!(
             add_first_and_last_digit_of_text_lines(
-                "1abc2
-pqr3stu8vwx
-a1b2c3d4e5f
-treb7uchet",
-                crate::AocPuzzle::PartOne
+                include_str!("../examples/day1/example1.txt"),
+                crate::AocPuzzle::PartOne,
+                Digits::Ascii
             ),
-            142
+            Ok(142)
         )
     }
 
@@ -90,16 +197,223 @@ treb7uchet",
     fn test_puzzle2() {
         assert_eq!(
             add_first_and_last_digit_of_text_lines(
-                "two1nine
-eightwothree
-abcone2threexyz
-xtwone3four
-4nineeightseven2
-zoneight234
-7pqrstsixteen",
-                AocPuzzle::PartTwo
+                include_str!("../examples/day1/example2.txt"),
+                AocPuzzle::PartTwo,
+                Digits::Ascii
             ),
-            281
+            Ok(281)
         )
     }
+
+    #[test]
+    fn test_lines_without_digits() {
+        let line = |s| add_first_and_last_digit_of_line(s, Digits::Ascii);
+        let words = |s| get_first_and_last_digit_with_english_words(s, Digits::Ascii);
+        assert_eq!(line("abc"), None);
+        assert_eq!(line(""), None);
+        // Numeric, but not a decimal digit
+        assert_eq!(line("½"), None);
+        assert_eq!(line("½7"), Some(77));
+        assert_eq!(words("xyz"), None);
+        assert_eq!(words("xtwoy"), Some(22));
+        assert_eq!(
+            add_first_and_last_digit_of_text_lines("a1b\nabc\n", AocPuzzle::PartOne, Digits::Ascii)
+                .map_err(|e| e.to_string()),
+            Err("line 2, column 1: expected a digit".to_string())
+        );
+        for puzzle in [AocPuzzle::PartOne, AocPuzzle::PartTwo] {
+            for digits in [Digits::Ascii, Digits::Unicode] {
+                assert_eq!(
+                    solve_with("1\n½\n", puzzle, digits).map_err(|e| e.to_string()),
+                    Err("line 2, column 1: expected a digit".to_string())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_unicode_digits() {
+        // Arabic-Indic 3, fullwidth 7, Devanagari 9
+        let line = "a٣b７c९";
+        assert_eq!(add_first_and_last_digit_of_line(line, Digits::Ascii), None);
+        assert_eq!(
+            add_first_and_last_digit_of_line(line, Digits::Unicode),
+            Some(39)
+        );
+        assert_eq!(
+            get_first_and_last_digit_with_english_words("one٣", Digits::Unicode),
+            Some(13)
+        );
+        // Still not fractions, superscripts or roman numerals
+        assert_eq!(
+            add_first_and_last_digit_of_line("½²Ⅻ", Digits::Unicode),
+            None
+        );
+        assert_eq!(Digits::Unicode.value('0'), Some(0));
+        assert_eq!(Digits::Unicode.value('/'), None);
+        assert_eq!(Digits::Unicode.value(':'), None);
+        assert_eq!(Digits::Unicode.value(char::MAX), None);
+    }
+
+    #[test]
+    fn test_decimal_zeros() {
+        assert!(DECIMAL_ZEROS.windows(2).all(|w| w[0] + 10 <= w[1]));
+        for zero in DECIMAL_ZEROS {
+            for value in 0..10 {
+                let c = char::from_u32(zero + value).unwrap();
+                assert_eq!(Digits::Unicode.value(c), Some(value));
+            }
+        }
+        // Over every code point: digits are numeric for std, and ASCII ones agree with to_digit
+        for c in (0..=char::MAX as u32).filter_map(char::from_u32) {
+            let value = Digits::Unicode.value(c);
+            assert!(value.is_none() || c.is_numeric(), "{c:?}");
+            if c.is_ascii() {
+                assert_eq!(value, c.to_digit(10), "{c:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_byte() {
+        let haystack = b"abcdefgh\nijklmnopqrstu\nv";
+        for start in 0..haystack.len() {
+            assert_eq!(
+                find_byte(&haystack[start..], b'\n'),
+                haystack[start..].iter().position(|b| *b == b'\n'),
+                "from {start}"
+            );
+        }
+        assert_eq!(find_byte(b"", b'\n'), None);
+        // Bytes just around the one searched for
+        assert_eq!(
+            find_byte(
+                &[0x09, 0x0b, 0x8a, 0xff, 0x8b, 0x0b, 0x09, 0x0a, 0x0a],
+                b'\n'
+            ),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn bytes_match_chars() {
+        let mut rng = Rng::new(3);
+        let mut inputs = vec![
+            String::new(),
+            "7".to_string(),
+            "a1b\n".to_string(),
+            "a1b\nc2d".to_string(),
+            "é9x\r\n½3\n".to_string(),
+            "a1\n\nb2\n".to_string(),
+            "a1\nxyz\n".to_string(),
+        ];
+        for seed in 0..20 {
+            let options = Options::from_strs(&[
+                "--seed",
+                &seed.to_string(),
+                "--lines",
+                &rng.range(1..50).to_string(),
+                "--length",
+                &rng.range(1..40).to_string(),
+            ]);
+            inputs.push(generate("day1", &options));
+        }
+        for input in inputs {
+            assert_eq!(
+                sum_ascii_lines(input.as_bytes()),
+                add_first_and_last_digit_of_text_lines(&input, AocPuzzle::PartOne, Digits::Ascii),
+                "{input:?}"
+            );
+        }
+    }
+
+    // First and last digit of every line, looking for the digit or the word
+    // at each position in turn
+    fn reference(input: &str, words: bool) -> u64 {
+        let names = [
+            "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+        ];
+        input
+            .lines()
+            .map(|line| {
+                let digits: Vec<u64> = (0..line.len())
+                    .filter_map(|i| {
+                        let rest = &line[i..];
+                        let digit = rest.chars().next().and_then(|c| c.to_digit(10));
+                        let word = (1..=9).zip(names).find(|(_, name)| rest.starts_with(name));
+                        match (digit, word) {
+                            (Some(d), _) => Some(d as u64),
+                            (None, Some((n, _))) if words => Some(n),
+                            _ => None,
+                        }
+                    })
+                    .collect();
+                10 * digits[0] + digits[digits.len() - 1]
+            })
+            .sum()
+    }
+
+    #[test]
+    fn parts_match_reference() {
+        for seed in 0..100 {
+            let mut rng = Rng::new(seed);
+            let input = generate(
+                "day1",
+                &Options::from_strs(&[
+                    "--seed",
+                    &seed.to_string(),
+                    "--lines",
+                    &rng.range(1..50).to_string(),
+                    "--length",
+                    &rng.range(1..30).to_string(),
+                    "--words",
+                    &(rng.range(0..5) as f64 / 10.0).to_string(),
+                ]),
+            );
+            let part_1 = solve(&input, AocPuzzle::PartOne);
+            assert_eq!(part_1, Ok(reference(&input, false)), "seed {seed}");
+            let part_2 = solve(&input, AocPuzzle::PartTwo);
+            assert_eq!(part_2, Ok(reference(&input, true)), "seed {seed}");
+        }
+    }
+
+    // cargo test --release bench_ -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_part_1() {
+        let input = generate(
+            "day1",
+            &Options::from_strs(&["--lines", "2000000", "--length", "40"]),
+        );
+        println!("{} MB", input.len() / 1_000_000);
+        let with_chars = measure("chars", 5, || {
+            add_first_and_last_digit_of_text_lines(&input, AocPuzzle::PartOne, Digits::Ascii)
+        });
+        let with_bytes = measure("bytes", 5, || sum_ascii_lines(input.as_bytes()));
+        assert_eq!(with_chars, with_bytes);
+    }
+
+    // The whole `aoc day1 part1` path, from the file to the answer. Before, the
+    // input was normalized twice and scanned char by char.
+    #[test]
+    #[ignore]
+    fn bench_solve_part_1() {
+        let input = generate(
+            "day1",
+            &Options::from_strs(&["--lines", "2000000", "--length", "40"]),
+        );
+        let path = env::temp_dir().join(format!("aoc-bench-day1-{}", process::id()));
+        fs::write(&path, &input).unwrap();
+        let before = measure("before", 5, || {
+            let input = normalize(&normalize(&fs::read_to_string(&path).unwrap()));
+            add_first_and_last_digit_of_text_lines(&input, AocPuzzle::PartOne, Digits::Ascii)
+                .map_err(SolveError::from)
+        });
+        let after = measure("after", 5, || {
+            let input = puzzle::read_input(&path).unwrap();
+            crate::solve("day1", AocPuzzle::PartOne, &input)
+        });
+        fs::remove_file(&path).unwrap();
+        assert_eq!(before, after);
+    }
 }
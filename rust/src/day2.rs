@@ -1,171 +1,343 @@
-use std::fs;
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
 
+use crate::parse::{ErrorKind, ParseError, Position, Text};
 use crate::puzzle::AocPuzzle;
 
-#[derive(Debug, PartialEq)]
-struct Set {
+// Counts of red, green and blue cubes
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Set {
     r: u32,
     g: u32,
     b: u32,
 }
 
-pub fn day_2(puzzle: AocPuzzle) -> u32 {
-    let puzzle_lines = get_puzzle();
+pub fn solve(input: &str, puzzle: AocPuzzle) -> Result<u64, ParseError> {
+    let games = parse_games(input)?;
     match puzzle {
-        AocPuzzle::PartOne => puzzle_1(&puzzle_lines),
-        AocPuzzle::PartTwo => puzzle_2(&puzzle_lines),
+        AocPuzzle::PartOne => Ok(puzzle_1(&games)),
+        AocPuzzle::PartTwo => puzzle_2(&games),
     }
 }
 
-fn get_puzzle() -> String {
-    fs::read_to_string("puzzle_2_1").expect("Should have been able to read the file")
-}
-
 impl Set {
     fn contains(&self, set: &Set) -> bool {
-        self.r >= set.r && self.g >= set.g && self.b >= set.b
+        set <= self
     }
 
-    fn power(&self) -> u32 {
-        self.r * self.g * self.b
+    // None when it doesn't fit in a u64
+    pub fn power(&self) -> Option<u64> {
+        (self.r as u64 * self.g as u64).checked_mul(self.b as u64)
     }
 
-    fn make(r: u32, g: u32, b: u32) -> Set {
+    pub fn make(r: u32, g: u32, b: u32) -> Set {
         Set { r, g, b }
     }
 
-    fn from_raw_str(s: &str) -> Set {
-        s.trim()
-            .split(',')
-            .fold(Set { b: 0, g: 0, r: 0 }, |mut acc, val| {
-                let s: Vec<_> = val.trim().split(' ').collect();
-                let value: u32 = s[0].parse().unwrap();
-                let color = s[1];
-                match color {
-                    "red" => acc.r = value,
-                    "green" => acc.g = value,
-                    "blue" => acc.b = value,
-                    _ => panic!("ILLEGAL COLOR {color}"),
-                }
-                acc
-            })
+    pub fn total(&self) -> u64 {
+        self.r as u64 + self.g as u64 + self.b as u64
+    }
+
+    // Union of the two bags, color by color
+    pub fn max(self, other: Set) -> Set {
+        Set::make(
+            self.r.max(other.r),
+            self.g.max(other.g),
+            self.b.max(other.b),
+        )
+    }
+
+    // Intersection of the two bags, color by color
+    pub fn min(self, other: Set) -> Set {
+        Set::make(
+            self.r.min(other.r),
+            self.g.min(other.g),
+            self.b.min(other.b),
+        )
+    }
+
+    fn from_raw_str(s: Text) -> Result<Set, ParseError> {
+        s.split(",").try_fold(Set::default(), |mut acc, cubes| {
+            let cubes = cubes.trim();
+            let [value, color] = cubes.words().collect::<Vec<_>>()[..] else {
+                return Err(cubes.expected("`<count> <color>`"));
+            };
+            let value: u32 = value.parse("cube count")?;
+            match color.as_str() {
+                "red" => acc.r = value,
+                "green" => acc.g = value,
+                "blue" => acc.b = value,
+                other => return Err(color.error(ErrorKind::Invalid("color", other.to_string()))),
+            }
+            Ok(acc)
+        })
+    }
+}
+
+// A color that would go past u32::MAX cubes stops there
+impl Add for Set {
+    type Output = Set;
+
+    fn add(self, other: Set) -> Set {
+        Set::make(
+            self.r.saturating_add(other.r),
+            self.g.saturating_add(other.g),
+            self.b.saturating_add(other.b),
+        )
+    }
+}
+
+// Taking out more cubes than there are leaves none of that color
+impl Sub for Set {
+    type Output = Set;
+
+    fn sub(self, other: Set) -> Set {
+        Set::make(
+            self.r.saturating_sub(other.r),
+            self.g.saturating_sub(other.g),
+            self.b.saturating_sub(other.b),
+        )
+    }
+}
+
+impl Sum for Set {
+    fn sum<I: Iterator<Item = Set>>(iter: I) -> Set {
+        iter.fold(Set::default(), Add::add)
+    }
+}
+
+// A set is below another when it has no more cubes of any color
+impl PartialOrd for Set {
+    fn partial_cmp(&self, other: &Set) -> Option<Ordering> {
+        let colors = [
+            self.r.cmp(&other.r),
+            self.g.cmp(&other.g),
+            self.b.cmp(&other.b),
+        ];
+        match (
+            colors.contains(&Ordering::Less),
+            colors.contains(&Ordering::Greater),
+        ) {
+            (false, false) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (true, true) => None,
+        }
+    }
+}
+
+// `4 red, 3 blue`, without the colors there are none of. An empty set is an
+// empty string.
+impl fmt::Display for Set {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cubes: Vec<String> = [(self.r, "red"), (self.g, "green"), (self.b, "blue")]
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, color)| format!("{count} {color}"))
+            .collect();
+        write!(f, "{}", cubes.join(", "))
+    }
+}
+
+impl FromStr for Set {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Set, ParseError> {
+        let text = Text::new(s);
+        match text.trim().is_empty() {
+            true => Ok(Set::default()),
+            false => Set::from_raw_str(text),
+        }
     }
 }
 
 #[derive(Debug, PartialEq)]
-struct Game {
+pub struct Game {
     id: u32,
     sets: Vec<Set>,
+    position: Position,
 }
 
 impl Game {
     fn can_be_set(&self, ref_set: Set) -> bool {
-        self.sets.iter().all(|set| ref_set.contains(&set)) // 12,14,13
+        self.sets.iter().all(|set| ref_set.contains(set)) // 12,14,13
     }
 
     fn get_minimum_set_of_cubes(&self) -> Set {
-        self.sets.iter().fold(Set::make(0, 0, 0), |acc, set| Set {
-            r: acc.r.max(set.r),
-            g: acc.g.max(set.g),
-            b: acc.b.max(set.b),
-        })
+        self.sets
+            .iter()
+            .fold(Set::default(), |acc, set| acc.max(*set))
     }
 
-    fn from_raw_str(s: &str) -> Game {
-        let raw_game_str: Vec<_> = s.split(':').collect();
-        let game_id = {
-            let a: Vec<_> = raw_game_str[0].split(' ').collect();
-            a[1].parse().unwrap()
-        };
+    fn overflow(&self, what: &'static str) -> ParseError {
+        ParseError {
+            position: self.position,
+            kind: ErrorKind::Overflow(what),
+        }
+    }
 
-        let sets = raw_game_str[1]
-            .split(';')
-            .map(|set| Set::from_raw_str(set))
-            .collect();
+    pub(crate) fn from_raw_str(s: Text) -> Result<Game, ParseError> {
+        let position = s.position();
+        let (raw_game, raw_sets) = s.header()?;
+        let id = raw_game.strip_prefix("Game ")?.trim().parse("game id")?;
+        let sets = raw_sets
+            .split(";")
+            .map(Set::from_raw_str)
+            .collect::<Result<_, _>>()?;
 
-        Game { id: game_id, sets }
+        Ok(Game { id, sets, position })
     }
 }
 
-fn puzzle_1(puzzle: &str) -> u32 {
-    puzzle
-        .lines()
-        .map(|l| Game::from_raw_str(l))
-        .fold(0, |acc, val| {
-            acc + match val.can_be_set(Set::make(12, 13, 14)) {
-                true => val.id,
-                false => 0,
+pub fn parse_games(input: &str) -> Result<Vec<Game>, ParseError> {
+    Text::new(input).lines().map(Game::from_raw_str).collect()
+}
+
+fn puzzle_1(games: &[Game]) -> u64 {
+    games.iter().fold(0, |acc, val| {
+        acc + match val.can_be_set(Set::make(12, 13, 14)) {
+            true => val.id as u64,
+            false => 0,
+        }
+    })
+}
+
+fn puzzle_2(games: &[Game]) -> Result<u64, ParseError> {
+    games.iter().try_fold(0_u64, |acc, val| {
+        let power = val
+            .get_minimum_set_of_cubes()
+            .power()
+            .ok_or_else(|| val.overflow("power"))?;
+        acc.checked_add(power)
+            .ok_or_else(|| val.overflow("sum of powers"))
+    })
+}
+
+// A bag and the ids of the games it makes possible
+#[derive(Debug, PartialEq)]
+pub struct Plan {
+    pub bag: Set,
+    pub games: Vec<u32>,
+}
+
+// Only the reds and greens some game needs are worth trying: once they are
+// set, the rest of the budget goes to blue. Each bag is then shrunk to the
+// smallest one for its games, so different plans have different games.
+// Returns the bags of at most `budget` cubes that make the most games possible.
+pub fn best_bags(games: &[Game], budget: u32) -> Vec<Plan> {
+    let minimums: Vec<(u32, Set)> = games
+        .iter()
+        .map(|game| (game.id, game.get_minimum_set_of_cubes()))
+        .collect();
+    let candidates = |color: fn(&Set) -> u32| {
+        let mut counts: Vec<u32> = minimums.iter().map(|(_, set)| color(set)).collect();
+        counts.push(0);
+        counts.sort();
+        counts.dedup();
+        counts
+    };
+
+    let mut plans: Vec<Plan> = vec![];
+    for r in candidates(|set| set.r) {
+        for g in candidates(|set| set.g) {
+            let Some(b) = r.checked_add(g).and_then(|rg| budget.checked_sub(rg)) else {
+                continue;
+            };
+            let possible: Vec<&(u32, Set)> = minimums
+                .iter()
+                .filter(|(_, minimum)| *minimum <= Set::make(r, g, b))
+                .collect();
+            let plan = Plan {
+                bag: possible
+                    .iter()
+                    .map(|(_, minimum)| *minimum)
+                    .fold(Set::default(), Set::max),
+                games: possible.iter().map(|(id, _)| *id).collect(),
+            };
+            match plans
+                .first()
+                .map(|best| plan.games.len().cmp(&best.games.len()))
+            {
+                None | Some(Ordering::Greater) => plans = vec![plan],
+                Some(Ordering::Equal) if !plans.contains(&plan) => plans.push(plan),
+                _ => {}
             }
-        })
+        }
+    }
+    plans.sort_by_key(|plan| (plan.bag.r, plan.bag.g, plan.bag.b));
+    plans
 }
 
-fn puzzle_2(puzzle: &str) -> u32 {
-    puzzle
-        .lines()
-        .map(|l| Game::from_raw_str(l))
-        .fold(0, |acc, val| acc + val.get_minimum_set_of_cubes().power())
+// The smallest bag that makes all the games in `ids` possible, or the first id
+// that isn't among the games
+pub fn cheapest_bag(games: &[Game], ids: &[u32]) -> Result<Set, u32> {
+    ids.iter().try_fold(Set::default(), |bag, id| {
+        let game = games.iter().find(|game| game.id == *id).ok_or(*id)?;
+        Ok(bag.max(game.get_minimum_set_of_cubes()))
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::day2::{puzzle_1, puzzle_2, Game, Set};
+    use std::cmp::Ordering;
+
+    use crate::day2::{
+        best_bags, cheapest_bag, parse_games, puzzle_1, puzzle_2, solve, Game, Plan, Set,
+    };
+    use crate::generate::{generate, Options};
+    use crate::parse::{ParseError, Position, Text};
+    use crate::rng::Rng;
+    use crate::AocPuzzle;
 
     #[test]
     fn test_puzzle_2() {
-        assert_eq!(
-            puzzle_2(
-                "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
-Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
-Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
-Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
-Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green"
-            ),
-            2286
-        );
+        assert_eq!(puzzle_2(&example_games()), Ok(2286));
     }
 
     #[test]
     fn test_minimal_set_of_cubes() {
-        let game = Game::from_raw_str("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green");
+        let game = Game::from_raw_str(Text::new(
+            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green",
+        ))
+        .unwrap();
         assert_eq!(game.get_minimum_set_of_cubes(), Set::make(4, 2, 6));
     }
 
     #[test]
     fn test_puzzle_1() {
-        assert_eq!(
-            puzzle_1(
-                "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
-Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
-Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
-Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
-Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green"
-            ),
-            8
-        );
+        assert_eq!(puzzle_1(&example_games()), 8);
     }
 
     #[test]
     fn test_parse_line() {
         assert_eq!(
-            Game::from_raw_str("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green"),
-            Game {
+            Game::from_raw_str(Text::new(
+                "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green"
+            )),
+            Ok(Game {
                 id: 1,
-                sets: vec![Set::make(4, 0, 3), Set::make(1, 2, 6), Set::make(0, 2, 0)]
-            }
+                sets: vec![Set::make(4, 0, 3), Set::make(1, 2, 6), Set::make(0, 2, 0)],
+                position: Position { line: 1, column: 1 },
+            })
         );
 
-        assert_eq!(Game::from_raw_str("Game 14: 1 red, 4 blue, 3 green; 2 red, 1 green, 1 blue; 1 red, 5 green, 1 blue; 3 red, 4 green, 4 blue
-        "), Game {
+        assert_eq!(Game::from_raw_str(Text::new("Game 14: 1 red, 4 blue, 3 green; 2 red, 1 green, 1 blue; 1 red, 5 green, 1 blue; 3 red, 4 green, 4 blue
+        ")), Ok(Game {
             id:14,
-            sets:vec![Set::make(1,3,4), Set::make(2,1,1), Set::make(1,5,1), Set::make(3,4,4)]
-        });
+            sets:vec![Set::make(1,3,4), Set::make(2,1,1), Set::make(1,5,1), Set::make(3,4,4)],
+            position: Position { line: 1, column: 1 },
+        }));
 
         assert_eq!(
-            Game::from_raw_str("Game 100: 2 blue, 1 red; 4 blue, 2 red, 1 green; 7 red, 5 blue; 2 red, 1 green, 5 blue"),
-            Game {
+            Game::from_raw_str(Text::new("Game 100: 2 blue, 1 red; 4 blue, 2 red, 1 green; 7 red, 5 blue; 2 red, 1 green, 5 blue")),
+            Ok(Game {
                 id: 100,
-                sets: vec![Set::make(1, 0, 2), Set::make(2, 1, 4), Set::make(7, 0, 5), Set::make(2, 1, 5)]
-            }
+                sets: vec![Set::make(1, 0, 2), Set::make(2, 1, 4), Set::make(7, 0, 5), Set::make(2, 1, 5)],
+                position: Position { line: 1, column: 1 },
+            })
         );
     }
 
@@ -175,6 +347,7 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green"
         let game1 = Game {
             id: 1,
             sets: vec![Set::make(4, 3, 0), Set::make(1, 2, 6), Set::make(0, 2, 0)],
+            position: Position { line: 1, column: 1 },
         };
 
         assert!(game1.can_be_set(Set::make(12, 13, 14)));
@@ -183,8 +356,269 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green"
         let game3 = Game {
             id: 2,
             sets: vec![Set::make(20, 8, 6), Set::make(4, 5, 13), Set::make(1, 5, 0)],
+            position: Position { line: 1, column: 1 },
         };
 
         assert!(!game3.can_be_set(Set::make(12, 13, 14)));
     }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |s| Game::from_raw_str(Text::new(s)).map_err(|e| e.to_string());
+        assert_eq!(error(""), Err("line 1, column 1: expected `:`".to_string()));
+        assert_eq!(
+            error("Game 1"),
+            Err("line 1, column 1: expected `:`".to_string())
+        );
+        assert_eq!(
+            error("Gam 1: 1 red"),
+            Err("line 1, column 1: expected `Game `".to_string())
+        );
+        assert_eq!(
+            error("Game x: 1 red"),
+            Err("line 1, column 6: invalid game id `x`".to_string())
+        );
+        assert_eq!(
+            error("Game 1: red"),
+            Err("line 1, column 9: expected `<count> <color>`".to_string())
+        );
+        assert_eq!(
+            error("Game 1:"),
+            Err("line 1, column 8: expected `<count> <color>`".to_string())
+        );
+        assert_eq!(
+            error("Game 1: 1 red; 99999999999 blue"),
+            Err("line 1, column 16: invalid cube count `99999999999`".to_string())
+        );
+        assert_eq!(
+            error("Game 1: 3 purple"),
+            Err("line 1, column 11: invalid color `purple`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_set_operators() {
+        let a = Set::make(4, 0, 3);
+        let b = Set::make(1, 2, 6);
+        assert_eq!(a + b, Set::make(5, 2, 9));
+        assert_eq!(a - b, Set::make(3, 0, 0));
+        assert_eq!(a.max(b), Set::make(4, 2, 6));
+        assert_eq!(a.min(b), Set::make(1, 0, 3));
+        assert_eq!([a, b, b].into_iter().sum::<Set>(), Set::make(6, 4, 15));
+        assert_eq!(Vec::<Set>::new().into_iter().sum::<Set>(), Set::default());
+        assert_eq!(
+            Set::make(u32::MAX, 1, 0) + Set::make(1, 1, 0),
+            Set::make(u32::MAX, 2, 0)
+        );
+
+        assert_eq!(a.partial_cmp(&b), None);
+        assert!(a.min(b) < a && a < a.max(b));
+        assert_eq!(a.partial_cmp(&a), Some(Ordering::Equal));
+        assert!(Set::make(12, 13, 14) > Set::make(12, 13, 13));
+        assert_eq!(
+            Set::make(20, 0, 0).partial_cmp(&Set::make(12, 13, 14)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_overflows() {
+        let error = |result: Result<u64, _>| result.map_err(|e: ParseError| e.to_string());
+        let max = u32::MAX;
+        assert_eq!(Set::make(70000, 70000, 1).power(), Some(4_900_000_000));
+        assert_eq!(Set::make(max, max, 1).total(), 2 * max as u64 + 1);
+        assert_eq!(Set::make(max, max, max).power(), None);
+
+        let games = parse_games(&format!("Game {max}: 1 blue\nGame {max}: 1 red\n")).unwrap();
+        assert_eq!(puzzle_1(&games), 2 * max as u64);
+
+        let input = "Game 1: 70000 red, 70000 green, 1 blue\n";
+        assert_eq!(
+            crate::solve("day2", AocPuzzle::PartTwo, input),
+            Ok(4_900_000_000)
+        );
+        let input = format!("Game 1: 1 red\nGame 2: {max} red, {max} green, {max} blue\n");
+        assert_eq!(
+            error(puzzle_2(&parse_games(&input).unwrap())),
+            Err("line 2, column 1: power overflows a u64".to_string())
+        );
+        let input =
+            format!("Game 1: {max} red, {max} green, 1 blue\nGame 2: {max} red, 2 green, 1 blue\n");
+        assert_eq!(puzzle_2(&parse_games(&input).unwrap()), Ok(u64::MAX));
+        let input = format!("{input}Game 3: 1 red, 1 green, 1 blue\n");
+        assert_eq!(
+            error(puzzle_2(&parse_games(&input).unwrap())),
+            Err("line 3, column 1: sum of powers overflows a u64".to_string())
+        );
+    }
+
+    #[test]
+    fn test_set_display_round_trip() {
+        assert_eq!(Set::make(4, 0, 3).to_string(), "4 red, 3 blue");
+        assert_eq!("3 blue, 4 red".parse(), Ok(Set::make(4, 0, 3)));
+        assert_eq!(Set::default().to_string(), "");
+        assert_eq!("".parse(), Ok(Set::default()));
+        assert_eq!(
+            "3 blue, 4 pink".parse::<Set>().map_err(|e| e.to_string()),
+            Err("line 1, column 11: invalid color `pink`".to_string())
+        );
+
+        let mut rng = Rng::new(2);
+        for _ in 0..200 {
+            let mut count = || match rng.chance(0.3) {
+                true => 0,
+                false => rng.range(1..100) as u32,
+            };
+            let set = Set::make(count(), count(), count());
+            assert_eq!(set.to_string().parse(), Ok(set), "{set}");
+        }
+    }
+
+    // Ids of the possible games and powers of the smallest bags, read with
+    // plain string splits
+    fn reference(input: &str) -> (u64, u64) {
+        let (mut ids, mut powers) = (0, 0);
+        for line in input.lines() {
+            let (game, sets) = line.split_once(": ").unwrap();
+            let id: u64 = game["Game ".len()..].parse().unwrap();
+            let mut bag = [0, 0, 0];
+            for cubes in sets.split("; ").flat_map(|set| set.split(", ")) {
+                let (count, color) = cubes.split_once(' ').unwrap();
+                let i = ["red", "green", "blue"].iter().position(|c| *c == color);
+                bag[i.unwrap()] = bag[i.unwrap()].max(count.parse().unwrap());
+            }
+            if bag[0] <= 12 && bag[1] <= 13 && bag[2] <= 14 {
+                ids += id;
+            }
+            powers += bag[0] * bag[1] * bag[2];
+        }
+        (ids, powers)
+    }
+
+    #[test]
+    fn parts_match_reference() {
+        for seed in 0..100 {
+            let mut rng = Rng::new(seed);
+            let input = generate(
+                "day2",
+                &Options::from_strs(&[
+                    "--seed",
+                    &seed.to_string(),
+                    "--games",
+                    &rng.range(1..50).to_string(),
+                    "--sets",
+                    &rng.range(1..8).to_string(),
+                    "--cubes",
+                    &rng.range(1..25).to_string(),
+                ]),
+            );
+            let (ids, powers) = reference(&input);
+            let part_1 = solve(&input, AocPuzzle::PartOne);
+            assert_eq!(part_1, Ok(ids), "seed {seed}");
+            let part_2 = solve(&input, AocPuzzle::PartTwo);
+            assert_eq!(part_2, Ok(powers), "seed {seed}");
+        }
+    }
+
+    fn example_games() -> Vec<Game> {
+        parse_games(include_str!("../examples/day2/example.txt")).unwrap()
+    }
+
+    #[test]
+    fn test_best_bags() {
+        let games = example_games();
+        // The bag of part 1 has 39 cubes and makes games 1, 2 and 5 possible
+        assert_eq!(
+            best_bags(&games, 39),
+            vec![
+                Plan {
+                    bag: Set::make(14, 3, 15),
+                    games: vec![1, 2, 4, 5]
+                },
+                Plan {
+                    bag: Set::make(20, 13, 6),
+                    games: vec![1, 2, 3, 5]
+                },
+            ]
+        );
+        assert_eq!(
+            best_bags(&games, 48),
+            vec![Plan {
+                bag: Set::make(20, 13, 15),
+                games: vec![1, 2, 3, 4, 5]
+            }]
+        );
+        assert_eq!(
+            best_bags(&games, 8),
+            vec![Plan {
+                bag: Set::make(1, 3, 4),
+                games: vec![2]
+            }]
+        );
+        assert_eq!(
+            best_bags(&games, 0),
+            vec![Plan {
+                bag: Set::default(),
+                games: vec![]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_cheapest_bag() {
+        let games = example_games();
+        assert_eq!(cheapest_bag(&games, &[1, 2, 5]), Ok(Set::make(6, 3, 6)));
+        assert_eq!(cheapest_bag(&games, &[]), Ok(Set::default()));
+        assert_eq!(cheapest_bag(&games, &[1, 9]), Err(9));
+    }
+
+    // Against every subset of a few games
+    #[test]
+    fn best_bags_match_subsets() {
+        let mut rng = Rng::new(5);
+        for seed in 0..30 {
+            let games: Vec<Game> = parse_games(&generate(
+                "day2",
+                &Options::from_strs(&[
+                    "--seed",
+                    &seed.to_string(),
+                    "--games",
+                    &rng.range(1..9).to_string(),
+                    "--cubes",
+                    "10",
+                ]),
+            ))
+            .unwrap();
+            let budget = rng.range(0..30) as u32;
+
+            let mut expected: Vec<Set> = vec![];
+            let mut most = 0;
+            for subset in 0..1_u32 << games.len() {
+                let ids: Vec<u32> = (0..games.len())
+                    .filter(|i| subset & (1 << i) != 0)
+                    .map(|i| games[i].id)
+                    .collect();
+                let bag = cheapest_bag(&games, &ids).unwrap();
+                // Only the subsets that are all the games their bag allows
+                let allowed = games.iter().filter(|g| g.can_be_set(bag)).count();
+                if bag.total() > budget as u64 || allowed != ids.len() || ids.len() < most {
+                    continue;
+                }
+                if ids.len() > most {
+                    most = ids.len();
+                    expected.clear();
+                }
+                expected.push(bag);
+            }
+            expected.sort_by_key(|bag| (bag.r, bag.g, bag.b));
+
+            let plans = best_bags(&games, budget);
+            assert_eq!(
+                plans.iter().map(|plan| plan.bag).collect::<Vec<_>>(),
+                expected,
+                "seed {seed}, budget {budget}"
+            );
+            assert!(plans.iter().all(|plan| plan.games.len() == most));
+        }
+    }
 }
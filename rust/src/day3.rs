@@ -1,27 +1,19 @@
-use std::fs;
-
+use crate::parse::{ParseError, Text};
 use crate::puzzle::AocPuzzle;
 
-//TODO: reduce complexity
-
 #[derive(PartialEq, Debug)]
-struct Num {
+pub(crate) struct Num {
     value: u32,
     length: usize,
     coordinates: (usize, usize),
 }
-pub fn day_3(puzzle: AocPuzzle) -> u32 {
-    let puzzle_file = get_puzzle();
+pub fn solve(input: &str, puzzle: AocPuzzle) -> Result<u32, ParseError> {
     match puzzle {
-        AocPuzzle::PartOne => part_1(&puzzle_file),
-        AocPuzzle::PartTwo => part_2(&puzzle_file),
+        AocPuzzle::PartOne => part_1(input),
+        AocPuzzle::PartTwo => part_2(input),
     }
 }
 
-fn get_puzzle() -> String {
-    fs::read_to_string("puzzle_3_1").expect("Should have been able to read the file")
-}
-
 pub trait Adjacent {
     fn is_adjacent(&self, coords: (usize, usize)) -> bool;
 }
@@ -35,7 +27,6 @@ impl Adjacent for (usize, usize) {
 impl Num {
     fn expands_coordinates(&self) -> Vec<(usize, usize)> {
         (self.coordinates.1..(self.coordinates.1 + self.length))
-            .into_iter()
             .map(|i| (self.coordinates.0, i))
             .collect()
     }
@@ -48,54 +39,46 @@ impl Num {
 }
 
 #[derive(PartialEq, Debug)]
-enum Token {
+pub(crate) enum Token {
     Number(Num),
     Symbol(char, (usize, usize)),
 }
 
-fn tokenize_lines(line: &str) -> Vec<Token> {
-    let mut acc = String::new();
+pub(crate) fn tokenize_lines(input: &str) -> Result<Vec<Token>, ParseError> {
     let mut tokens: Vec<Token> = Vec::new();
-    for (line_index, line) in line.lines().enumerate() {
-        for (column, c) in line.chars().enumerate() {
-            if c.is_digit(10) {
-                acc.push(c);
-
-                if column != line.len() - 1 {
-                    continue;
-                }
-            }
-
-            let coeff = match c.is_digit(10) {
-                true => 1,
-                false => 0,
-            };
-
-            if let Ok(num) = acc.parse::<u32>() {
-                let length = acc.len();
-                tokens.push(Token::Number(Num {
-                    value: num,
-                    length: length.try_into().unwrap(),
-                    coordinates: (line_index, (column - length) + coeff),
-                }));
-                acc.clear();
+    for (line_index, line) in Text::new(input).lines().enumerate() {
+        let text = line.as_str();
+        // Byte offset and column where the current run of digits starts
+        let mut digits: Option<(usize, usize)> = None;
+        let push_number = |tokens: &mut Vec<Token>, digits: &str, column| {
+            tokens.push(Token::Number(Num {
+                value: line.sub(digits).parse("number")?,
+                length: digits.len(),
+                coordinates: (line_index, column),
+            }));
+            Ok(())
+        };
+        for (column, (offset, c)) in text.char_indices().enumerate() {
+            if c.is_ascii_digit() {
+                digits.get_or_insert((offset, column));
+                continue;
             }
-
-            if c.is_digit(10) {
-                break;
+            if let Some((start, start_column)) = digits.take() {
+                push_number(&mut tokens, &text[start..offset], start_column)?;
             }
-
-            match c {
-                '.' => continue,
-                symbol => tokens.push(Token::Symbol(symbol, (line_index, column))),
+            if c != '.' {
+                tokens.push(Token::Symbol(c, (line_index, column)));
             }
         }
+        if let Some((start, start_column)) = digits {
+            push_number(&mut tokens, &text[start..], start_column)?;
+        }
     }
-    tokens
+    Ok(tokens)
 }
 
-fn part_2(input: &str) -> u32 {
-    let tokens = tokenize_lines(input);
+fn part_2(input: &str) -> Result<u32, ParseError> {
+    let tokens = tokenize_lines(input)?;
     let gears_positions: &Vec<_> = &tokens
         .iter()
         .filter_map(|x| match x {
@@ -104,7 +87,7 @@ fn part_2(input: &str) -> u32 {
         })
         .collect();
 
-    gears_positions.iter().fold(0, |acc, s| {
+    Ok(gears_positions.iter().fold(0, |acc, s| {
         let compatibles: Vec<_> = tokens
             .iter()
             .filter_map(|x| match x {
@@ -123,11 +106,11 @@ fn part_2(input: &str) -> u32 {
             2 => compatibles[0] * compatibles[1],
             _ => 0,
         }
-    })
+    }))
 }
 
-fn part_1(input: &str) -> u32 {
-    let tokens = tokenize_lines(input);
+fn part_1(input: &str) -> Result<u32, ParseError> {
+    let tokens = tokenize_lines(input)?;
     let symbols_positions: &Vec<_> = &tokens
         .iter()
         .filter_map(|x| match x {
@@ -140,58 +123,41 @@ fn part_1(input: &str) -> u32 {
     for token in &tokens {
         match token {
             Token::Number(n) => {
-                for s in symbols_positions.clone() {
-                    if n.coordinates_compatible(*s) {
-                        sum = sum + n.value
-                    }
+                if symbols_positions
+                    .iter()
+                    .any(|s| n.coordinates_compatible(**s))
+                {
+                    sum += n.value
                 }
             }
             _ => continue,
         }
     }
 
-    sum
+    Ok(sum)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::day3::{part_1, part_2, tokenize_lines, Num, Token};
+    use crate::generate::{generate, Options};
+    use crate::rng::Rng;
 
     #[test]
     fn test_part_1() {
         assert_eq!(
-            part_1(
-                "467..114..
-...*......
-..35..633.
-......#...
-617*......
-.....+.58.
-..592.....
-......755.
-...$.*....
-.664.598.."
-            ),
-            4361
-        )
+            part_1(include_str!("../examples/day3/example.txt")),
+            Ok(4361)
+        );
+        // Counted once, whatever the number of symbols around
+        assert_eq!(part_1("12.\n*@."), Ok(12))
     }
 
     #[test]
     fn test_part_2() {
         assert_eq!(
-            part_2(
-                "467..114..
-...*......
-..35..633.
-......#...
-617*......
-.....+.58.
-..592.....
-......755.
-...$.*....
-.664.598.."
-            ),
-            467_835
+            part_2(include_str!("../examples/day3/example.txt")),
+            Ok(467_835)
         )
     }
 
@@ -240,26 +206,15 @@ mod tests {
     fn test_parse_line() {
         assert_eq!(
             tokenize_lines(".867"),
-            vec![Token::Number(Num {
+            Ok(vec![Token::Number(Num {
                 value: 867,
                 coordinates: (0, 1),
                 length: 3
-            }),]
+            }),])
         );
         assert_eq!(
-            tokenize_lines(
-                "467..114..
-...*......
-..35..633.
-......#...
-617*......
-.....+.58.
-..592.....
-......755.
-...$.*....
-.664.598..",
-            ),
-            vec![
+            tokenize_lines(include_str!("../examples/day3/example.txt"),),
+            Ok(vec![
                 // Line 1
                 Token::Number(Num {
                     value: 467,
@@ -326,7 +281,163 @@ mod tests {
                     length: 3,
                     coordinates: (9, 5)
                 }),
-            ]
+            ])
         )
     }
+
+    fn random_schematic(seed: u64) -> String {
+        let mut rng = Rng::new(seed);
+        let (width, height) = (rng.range(1..30), rng.range(1..30));
+        let density = rng.range(0..40) as f64 / 100.0;
+        generate(
+            "day3",
+            &Options::from_strs(&[
+                "--seed",
+                &seed.to_string(),
+                "--width",
+                &width.to_string(),
+                "--height",
+                &height.to_string(),
+                "--density",
+                &density.to_string(),
+            ]),
+        )
+    }
+
+    // Digit runs with the coordinates of their cells, read straight from the grid
+    fn reference_numbers(grid: &[Vec<char>]) -> Vec<(u32, Vec<(usize, usize)>)> {
+        let mut numbers = vec![];
+        for (y, row) in grid.iter().enumerate() {
+            let mut x = 0;
+            while x < row.len() {
+                let start = x;
+                while x < row.len() && row[x].is_ascii_digit() {
+                    x += 1;
+                }
+                if start < x {
+                    let value = row[start..x].iter().collect::<String>().parse().unwrap();
+                    numbers.push((value, (start..x).map(|x| (y, x)).collect()));
+                } else {
+                    x += 1;
+                }
+            }
+        }
+        numbers
+    }
+
+    fn neighbours(grid: &[Vec<char>], (y, x): (usize, usize)) -> Vec<(usize, usize)> {
+        let mut cells = vec![];
+        for ny in y.saturating_sub(1)..=y + 1 {
+            for nx in x.saturating_sub(1)..=x + 1 {
+                if grid.get(ny).is_some_and(|row| nx < row.len()) && (ny, nx) != (y, x) {
+                    cells.push((ny, nx));
+                }
+            }
+        }
+        cells
+    }
+
+    fn reference_part_1(input: &str) -> u32 {
+        let grid: Vec<Vec<char>> = input.lines().map(|l| l.chars().collect()).collect();
+        let is_symbol = |c: char| c != '.' && !c.is_ascii_digit();
+        reference_numbers(&grid)
+            .into_iter()
+            .filter(|(_, cells)| {
+                cells.iter().any(|&cell| {
+                    neighbours(&grid, cell)
+                        .into_iter()
+                        .any(|(y, x)| is_symbol(grid[y][x]))
+                })
+            })
+            .map(|(value, _)| value)
+            .sum()
+    }
+
+    fn reference_part_2(input: &str) -> u32 {
+        let grid: Vec<Vec<char>> = input.lines().map(|l| l.chars().collect()).collect();
+        let numbers = reference_numbers(&grid);
+        let mut sum = 0;
+        for (y, row) in grid.iter().enumerate() {
+            for (x, _) in row.iter().enumerate().filter(|(_, c)| **c == '*') {
+                let around = neighbours(&grid, (y, x));
+                let adjacent: Vec<u32> = numbers
+                    .iter()
+                    .filter(|(_, cells)| cells.iter().any(|c| around.contains(c)))
+                    .map(|(value, _)| *value)
+                    .collect();
+                if let [a, b] = adjacent[..] {
+                    sum += a * b;
+                }
+            }
+        }
+        sum
+    }
+
+    #[test]
+    fn tokens_rebuild_the_schematic() {
+        for seed in 0..200 {
+            let input = random_schematic(seed);
+            let mut grid: Vec<Vec<char>> = input.lines().map(|l| vec!['.'; l.len()]).collect();
+            for token in tokenize_lines(&input).unwrap() {
+                match token {
+                    Token::Number(n) => {
+                        let digits = n.value.to_string();
+                        assert_eq!(digits.len(), n.length, "seed {seed}: {n:?}");
+                        for (i, d) in digits.chars().enumerate() {
+                            grid[n.coordinates.0][n.coordinates.1 + i] = d;
+                        }
+                    }
+                    Token::Symbol(c, (y, x)) => grid[y][x] = c,
+                }
+            }
+            let rebuilt: Vec<String> = grid.into_iter().map(|r| r.into_iter().collect()).collect();
+            assert_eq!(rebuilt, input.lines().collect::<Vec<_>>(), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn parts_match_reference() {
+        for seed in 0..200 {
+            let input = random_schematic(seed);
+            assert_eq!(part_1(&input), Ok(reference_part_1(&input)), "seed {seed}");
+            assert_eq!(part_2(&input), Ok(reference_part_2(&input)), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn test_tokenizer_edge_cases() {
+        // A number filling the whole line
+        assert_eq!(
+            tokenize_lines("5\n\n42"),
+            Ok(vec![
+                Token::Number(Num {
+                    value: 5,
+                    length: 1,
+                    coordinates: (0, 0)
+                }),
+                Token::Number(Num {
+                    value: 42,
+                    length: 2,
+                    coordinates: (2, 0)
+                }),
+            ])
+        );
+        // Columns count chars, not bytes
+        assert_eq!(
+            tokenize_lines("é1\n*"),
+            Ok(vec![
+                Token::Symbol('é', (0, 0)),
+                Token::Number(Num {
+                    value: 1,
+                    length: 1,
+                    coordinates: (0, 1)
+                }),
+                Token::Symbol('*', (1, 0)),
+            ])
+        );
+        assert_eq!(
+            tokenize_lines("..*\n.99999999999.").map_err(|e| e.to_string()),
+            Err("line 2, column 2: invalid number `99999999999`".to_string())
+        );
+    }
 }
@@ -1,141 +1,312 @@
-use std::fs;
+use std::collections::HashSet;
+use std::fmt;
 
+use crate::parse::{ErrorKind, ParseError, Position, Text};
 use crate::puzzle::AocPuzzle;
 
-pub fn day_4(puzzle: AocPuzzle) -> u32 {
-    let puzzle_file = get_puzzle();
+pub fn solve(input: &str, puzzle: AocPuzzle) -> Result<u64, ParseError> {
     match puzzle {
-        AocPuzzle::PartOne => part_1(&puzzle_file),
-        AocPuzzle::PartTwo => part_2(&puzzle_file),
+        AocPuzzle::PartOne => part_1(input),
+        AocPuzzle::PartTwo => part_2(input),
     }
 }
 
-trait ParseVec {
-    fn parse_vec(&self) -> Vec<u8>;
+// One bit per possible u8 value
+#[derive(PartialEq, Debug, Default)]
+struct NumberSet {
+    bits: [u64; 4],
 }
 
-impl ParseVec for &str {
-    fn parse_vec(&self) -> Vec<u8> {
-        self.trim().split(' ').flat_map(|x| x.parse()).collect()
+impl NumberSet {
+    fn insert(&mut self, n: u8) {
+        self.bits[(n >> 6) as usize] |= 1 << (n & 63);
+    }
+
+    fn contains(&self, n: u8) -> bool {
+        self.bits[(n >> 6) as usize] & (1 << (n & 63)) != 0
+    }
+
+    fn intersection_count(&self, numbers: &[u8]) -> usize {
+        numbers.iter().filter(|n| self.contains(**n)).count()
+    }
+}
+
+impl FromIterator<u8> for NumberSet {
+    fn from_iter<T: IntoIterator<Item = u8>>(iter: T) -> Self {
+        let mut set = NumberSet::default();
+        for n in iter {
+            set.insert(n);
+        }
+        set
     }
 }
 
 #[derive(PartialEq, Debug)]
-struct Card {
-    id: u8,
-    winning: Vec<u8>,
+pub(crate) struct Card {
+    id: u32,
+    winning: NumberSet,
     numbers: Vec<u8>,
+    position: Position,
 }
 
 impl Card {
-    fn is_winning_number(&self, n: &u8) -> bool {
-        self.winning.contains(n)
+    fn count_matches(&self) -> usize {
+        self.winning.intersection_count(&self.numbers)
     }
 
-    fn get_winning_tickets(&self) -> Vec<&u8> {
-        self.numbers
-            .iter()
-            .filter(|n| self.is_winning_number(n))
-            .collect()
+    fn get_card_score(&self) -> Result<u64, ParseError> {
+        match self.count_matches() {
+            0 => Ok(0),
+            n => 1_u64
+                .checked_shl((n as u32) - 1)
+                .ok_or_else(|| self.overflow("score")),
+        }
     }
 
-    fn get_card_score(&self) -> u32 {
-        match self.get_winning_tickets().len() {
-            0 => 0,
-            n => 2_u32.pow((n as u32) - 1),
+    fn overflow(&self, what: &'static str) -> ParseError {
+        ParseError {
+            position: self.position,
+            kind: ErrorKind::Overflow(what),
         }
     }
 
-    fn from_raw_line(line: &str) -> Card {
-        let (left_part, right_part) = line.split_once('|').unwrap();
-        let (raw_card, raw_winning) = left_part.split_once(':').unwrap();
-        let id: u8 = raw_card.split_once(' ').unwrap().1.trim().parse().unwrap();
+    pub(crate) fn from_raw_line(line: Text) -> Result<Card, ParseError> {
+        let (raw_card, raw_numbers) = line.header()?;
+        let id = raw_card.strip_prefix("Card")?.trim().parse("card id")?;
+        let (raw_winning, raw_numbers) = raw_numbers.split_once("|")?;
 
-        Card {
+        Ok(Card {
             id,
-            numbers: right_part.parse_vec(),
-            winning: raw_winning.parse_vec(),
-        }
+            numbers: raw_numbers.numbers()?,
+            winning: raw_winning.numbers()?.into_iter().collect(),
+            position: line.position(),
+        })
     }
 }
 
-fn get_puzzle() -> String {
-    fs::read_to_string("puzzle_4_1").expect("Should have been able to read the file")
+// Cards may come shuffled or filtered: they are played in id order, and copies
+// are only won for the ids that are actually present.
+pub(crate) fn parse_cards(input: &str) -> Result<Vec<Card>, ParseError> {
+    let mut ids = HashSet::new();
+    let mut cards = Text::new(input)
+        .lines()
+        .map(|line| {
+            let card = Card::from_raw_line(line)?;
+            match ids.insert(card.id) {
+                true => Ok(card),
+                false => Err(line.error(ErrorKind::Duplicate("card", card.id.to_string()))),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    cards.sort_by_key(|c| c.id);
+    Ok(cards)
 }
 
 struct CardsRegistry {
-    cards_indexes: Vec<u32>,
+    cards_indexes: Vec<u64>,
+    sources: Vec<Vec<(u32, u64)>>,
 }
 
 impl CardsRegistry {
-    fn add_index(&mut self, index: usize) {
+    fn add_copies(&mut self, index: usize, from: u32, count: u64) -> Option<()> {
         let x = self.cards_indexes.get_mut(index).unwrap();
-        *x += 1
+        *x = x.checked_add(count)?;
+        self.sources[index].push((from, count));
+        Some(())
     }
     fn new(len: usize) -> CardsRegistry {
         CardsRegistry {
             cards_indexes: vec![0; len],
+            sources: vec![vec![]; len],
         }
     }
-    fn get_index(&self, index: usize) -> u32 {
+    fn get_index(&self, index: usize) -> u64 {
         *self.cards_indexes.get(index).unwrap_or(&0)
     }
 
-    fn get_count(&self) -> u32 {
-        self.cards_indexes.iter().sum::<u32>()
+    fn get_sources(&self, index: usize) -> &[(u32, u64)] {
+        self.sources.get(index).map_or(&[], |s| s.as_slice())
     }
 }
 
-fn part_2(input: &str) -> u32 {
-    let mut registry = CardsRegistry::new(input.len());
+// The registry is indexed by position in the sorted cards, not by id
+fn play(input: &str) -> Result<(Vec<Card>, CardsRegistry), ParseError> {
+    let cards = parse_cards(input)?;
+    let mut registry = CardsRegistry::new(cards.len());
+    let last_id = cards.last().map_or(0, |c| c.id);
 
-    for line in input.lines() {
-        let card = Card::from_raw_line(line);
-        let num_of_copies = registry.get_index(card.id as usize);
-        let winning = card.get_winning_tickets().iter().count() as u8;
+    for (index, card) in cards.iter().enumerate() {
+        let num_of_copies = registry
+            .get_index(index)
+            .checked_add(1)
+            .ok_or_else(|| card.overflow("number of copies"))?;
+        let last_won = card
+            .id
+            .saturating_add(card.count_matches() as u32)
+            .min(last_id);
 
-        for _ in 0..=num_of_copies {
-            for c in card.id + 1..=card.id + winning {
-                registry.add_index(c as usize)
-            }
+        for (c, won) in cards
+            .iter()
+            .enumerate()
+            .skip(index + 1)
+            .take_while(|(_, c)| c.id <= last_won)
+        {
+            registry
+                .add_copies(c, card.id, num_of_copies)
+                .ok_or_else(|| won.overflow("number of copies"))?;
         }
     }
-    registry.get_count() + input.lines().count() as u32
+    Ok((cards, registry))
 }
 
-fn part_1(input: &str) -> u32 {
-    input.lines().fold(0, |acc, val| {
-        acc + Card::from_raw_line(val).get_card_score()
+fn total_score(cards: &[Card]) -> Result<u64, ParseError> {
+    cards.iter().try_fold(0_u64, |total, card| {
+        total
+            .checked_add(card.get_card_score()?)
+            .ok_or_else(|| card.overflow("total score"))
     })
 }
 
+// Every card counts once, plus the copies it won
+fn total_copies(cards: &[Card], registry: &CardsRegistry) -> Result<u64, ParseError> {
+    cards
+        .iter()
+        .enumerate()
+        .try_fold(0_u64, |total, (index, card)| {
+            registry
+                .get_index(index)
+                .checked_add(1)
+                .and_then(|copies| total.checked_add(copies))
+                .ok_or_else(|| card.overflow("total number of cards"))
+        })
+}
+
+fn part_2(input: &str) -> Result<u64, ParseError> {
+    let (cards, registry) = play(input)?;
+    total_copies(&cards, &registry)
+}
+
+#[derive(PartialEq, Debug)]
+struct CardTrace {
+    id: u32,
+    matches: usize,
+    score: u64,
+    copies: u64,
+    won_from: Vec<(u32, u64)>,
+}
+
+struct CardsReport {
+    cards: Vec<CardTrace>,
+    total_score: u64,
+    total_copies: u64,
+}
+
+impl CardsReport {
+    fn from_raw_str(input: &str) -> Result<CardsReport, ParseError> {
+        let (cards, registry) = play(input)?;
+        // Both totals fit, so every card's score and copies do too
+        let total_score = total_score(&cards)?;
+        let total_copies = total_copies(&cards, &registry)?;
+        let cards = cards
+            .iter()
+            .enumerate()
+            .map(|(index, card)| {
+                Ok(CardTrace {
+                    id: card.id,
+                    matches: card.count_matches(),
+                    score: card.get_card_score()?,
+                    copies: registry.get_index(index) + 1,
+                    won_from: registry.get_sources(index).to_vec(),
+                })
+            })
+            .collect::<Result<_, ParseError>>()?;
+        Ok(CardsReport {
+            cards,
+            total_score,
+            total_copies,
+        })
+    }
+}
+
+impl fmt::Display for CardsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for card in &self.cards {
+            write!(
+                f,
+                "Card {:>3}: {:>2} matches, score {:>5}, {:>8} copies",
+                card.id, card.matches, card.score, card.copies
+            )?;
+            if !card.won_from.is_empty() {
+                let sources: Vec<_> = card
+                    .won_from
+                    .iter()
+                    .map(|(id, count)| format!("{count} from card {id}"))
+                    .collect();
+                write!(f, " (won {})", sources.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(
+            f,
+            "Total: {} cards, score {}, {} copies",
+            self.cards.len(),
+            self.total_score,
+            self.total_copies
+        )
+    }
+}
+
+pub fn report(input: &str) -> Result<String, ParseError> {
+    Ok(CardsReport::from_raw_str(input)?.to_string())
+}
+
+fn part_1(input: &str) -> Result<u64, ParseError> {
+    total_score(&parse_cards(input)?)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::vec;
 
-    use crate::day4::{part_1, part_2};
+    use std::hint::black_box;
 
-    use super::Card;
+    use crate::bench::measure;
+    use crate::day4::{part_1, part_2, report};
+    use crate::generate::{generate, Options};
+    use crate::parse::ParseError;
+    use crate::parse::{Position, Text};
+    use crate::rng::Rng;
+
+    use super::{parse_cards, Card, CardTrace, CardsReport, NumberSet};
 
     #[test]
     fn test_parse() {
-        let card1 = Card::from_raw_line("Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53");
+        let card1 = Card::from_raw_line(Text::new(
+            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+        ))
+        .unwrap();
         assert_eq!(
             card1,
             Card {
                 id: 1,
-                winning: vec![41, 48, 83, 86, 17],
+                winning: NumberSet::from_iter([41, 48, 83, 86, 17]),
                 numbers: vec![83, 86, 6, 31, 17, 9, 48, 53],
+                position: Position { line: 1, column: 1 },
             }
         );
 
-        let card2 = Card::from_raw_line("Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1");
+        let card2 = Card::from_raw_line(Text::new(
+            "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
+        ))
+        .unwrap();
         assert_eq!(
             card2,
             Card {
                 id: 3,
-                winning: vec![1, 21, 53, 59, 44],
-                numbers: vec![69, 82, 63, 72, 16, 21, 14, 1]
+                winning: NumberSet::from_iter([1, 21, 53, 59, 44]),
+                numbers: vec![69, 82, 63, 72, 16, 21, 14, 1],
+                position: Position { line: 1, column: 1 },
             }
         )
     }
@@ -144,48 +315,271 @@ mod tests {
     fn test_sum() {
         let card = Card {
             id: 1,
-            winning: vec![41, 48, 83, 86, 17],
+            winning: NumberSet::from_iter([41, 48, 83, 86, 17]),
             numbers: vec![83, 86, 6, 31, 17, 9, 48, 53],
+            position: Position { line: 1, column: 1 },
         };
 
-        assert_eq!(card.get_card_score(), 8);
+        assert_eq!(card.get_card_score(), Ok(8));
 
         let card = Card {
             id: 3,
-            winning: vec![1, 21, 53, 59, 44],
+            winning: NumberSet::from_iter([1, 21, 53, 59, 44]),
             numbers: vec![69, 82, 63, 72, 16, 21, 14, 1],
+            position: Position { line: 1, column: 1 },
         };
 
-        assert_eq!(card.get_card_score(), 2);
+        assert_eq!(card.get_card_score(), Ok(2));
     }
 
     #[test]
     fn test_part_1() {
+        assert_eq!(part_1(include_str!("../examples/day4/example.txt")), Ok(13))
+    }
+
+    #[test]
+    fn test_part_2() {
+        assert_eq!(part_2(include_str!("../examples/day4/example.txt")), Ok(30))
+    }
+
+    #[test]
+    fn test_number_set() {
+        let set = NumberSet::from_iter([0, 41, 63, 64, 255]);
+        assert!(set.contains(0));
+        assert!(set.contains(63));
+        assert!(set.contains(64));
+        assert!(set.contains(255));
+        assert!(!set.contains(42));
+        assert_eq!(set.intersection_count(&[41, 42, 64, 64, 1]), 3);
+
+        let card = Card::from_raw_line(Text::new(
+            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+        ))
+        .unwrap();
+        assert_eq!(card.count_matches(), 4);
+    }
+
+    // cargo test --release bench_ -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_count_matches() {
+        let winning: Vec<u8> = (0..200).map(|i| ((i * 37 + 11) % 256) as u8).collect();
+        let cards: Vec<Card> = (0..1_000)
+            .map(|c| Card {
+                id: 1,
+                winning: winning.iter().copied().collect(),
+                numbers: (0..300).map(|i| ((i * 53 + c * 7) % 256) as u8).collect(),
+                position: Position { line: 1, column: 1 },
+            })
+            .collect();
+
+        let with_vec = measure("Vec::contains", 10, || {
+            cards
+                .iter()
+                .map(|c| c.numbers.iter().filter(|n| winning.contains(n)).count())
+                .sum::<usize>()
+        });
+        let with_set = measure("NumberSet", 10, || {
+            cards
+                .iter()
+                .map(|c| black_box(c).count_matches())
+                .sum::<usize>()
+        });
+        assert_eq!(with_vec, with_set);
+    }
+
+    #[test]
+    fn test_report() {
+        let report =
+            CardsReport::from_raw_str(include_str!("../examples/day4/example.txt")).unwrap();
+
+        assert_eq!(
+            report.cards[3],
+            CardTrace {
+                id: 4,
+                matches: 1,
+                score: 1,
+                copies: 8,
+                won_from: vec![(1, 1), (2, 2), (3, 4)],
+            }
+        );
+        assert_eq!(report.total_score, 13);
+        assert_eq!(report.total_copies, 30);
+        assert!(report
+            .to_string()
+            .ends_with("Total: 6 cards, score 13, 30 copies\n"));
+    }
+
+    #[test]
+    fn test_shuffled_and_filtered_cards() {
+        // Example cards in reverse order
+        assert_eq!(
+            part_2(
+                "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53"
+            ),
+            Ok(30)
+        );
+
+        // Without card 3: card 1 wins copies of 2, 4 and 5, card 2 of 4, card 4 of 5
         assert_eq!(
-            part_1(
+            part_2(
                 "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
 Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
-Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
 Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
 Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
 Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11"
             ),
-            13
-        )
+            Ok(14)
+        );
     }
 
     #[test]
-    fn test_part_2() {
+    fn test_duplicate_ids() {
         assert_eq!(
-            part_2(
+            parse_cards(
                 "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
 Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
-Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
-Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
-Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
-Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11"
-            ),
-            30
-        )
+Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53"
+            )
+            .map_err(|e| e.to_string()),
+            Err("line 3, column 1: duplicate card `1`".to_string())
+        );
+    }
+
+    // Card id and number of matches, without going through `Card`
+    fn reference_matches(input: &str) -> HashMap<u32, u32> {
+        input
+            .lines()
+            .map(|line| {
+                let (card, numbers) = line.split_once(':').unwrap();
+                let (winning, mine) = numbers.split_once('|').unwrap();
+                let winning: Vec<&str> = winning.split_whitespace().collect();
+                let matches = mine
+                    .split_whitespace()
+                    .filter(|n| winning.contains(n))
+                    .count();
+                let id = card.trim_start_matches("Card").trim().parse().unwrap();
+                (id, matches as u32)
+            })
+            .collect()
+    }
+
+    // Every card won is played again, recursively
+    fn reference_copies(matches: &HashMap<u32, u32>, id: u32) -> u64 {
+        1 + (id + 1..=id + matches[&id])
+            .filter(|won| matches.contains_key(won))
+            .map(|won| reference_copies(matches, won))
+            .sum::<u64>()
+    }
+
+    #[test]
+    fn parts_match_reference() {
+        for seed in 0..100 {
+            let mut rng = Rng::new(seed);
+            let cards = rng.range(1..30);
+            let input = generate(
+                "day4",
+                &Options::from_strs(&[
+                    "--seed",
+                    &seed.to_string(),
+                    "--cards",
+                    &cards.to_string(),
+                    "--match-rate",
+                    "0.08",
+                ]),
+            );
+            let mut lines: Vec<&str> = input.lines().filter(|_| rng.chance(0.8)).collect();
+            rng.shuffle(&mut lines);
+            let input = lines.join("\n");
+
+            let matches = reference_matches(&input);
+            let score: u64 = matches.values().map(|&m| (1 << m) >> 1).sum();
+            let copies: u64 = matches
+                .keys()
+                .map(|&id| reference_copies(&matches, id))
+                .sum();
+            assert_eq!(part_1(&input), Ok(score), "seed {seed}");
+            assert_eq!(part_2(&input), Ok(copies), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn test_last_card_id() {
+        let input = "Card 4294967294: 1 2 | 1 2\nCard 4294967295: 1 | 1";
+        assert_eq!(part_1(input), Ok(3));
+        assert_eq!(part_2(input), Ok(3));
+    }
+
+    #[test]
+    fn test_overflows() {
+        let card = |id, matches| {
+            let numbers: Vec<String> = (1..=matches).map(|n: u32| n.to_string()).collect();
+            format!("Card {id}: {} | {}", numbers.join(" "), numbers.join(" "))
+        };
+        let error = |result: Result<u64, _>| result.map_err(|e: ParseError| e.to_string());
+
+        let input = [card(1, 64), card(2, 1)].join("\n");
+        assert_eq!(part_1(&input), Ok((1 << 63) + 1));
+        let input = [card(1, 65), card(2, 1)].join("\n");
+        assert_eq!(
+            error(part_1(&input)),
+            Err("line 1, column 1: score overflows a u64".to_string())
+        );
+        assert_eq!(part_2(&input), Ok(3));
+        let input = [card(1, 64), card(2, 64)].join("\n");
+        assert_eq!(
+            error(part_1(&input)),
+            Err("line 2, column 1: total score overflows a u64".to_string())
+        );
+
+        // Card n has 2^(n-1) copies, 2^64 - 1 for the first 64 cards together
+        let mut input: Vec<String> = (1..=64).map(|id| card(id, 66)).collect();
+        input.push(card(200, 1));
+        let input = input.join("\n");
+        assert_eq!(
+            error(part_2(&input)),
+            Err("line 65, column 1: total number of cards overflows a u64".to_string())
+        );
+        let input: Vec<String> = (1..=65).map(|id| card(id, 66)).collect();
+        let input = input.join("\n");
+        assert_eq!(
+            error(part_2(&input)),
+            Err("line 65, column 1: number of copies overflows a u64".to_string())
+        );
+        assert!(report(&input).is_err());
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        let error = |s| Card::from_raw_line(Text::new(s)).map_err(|e| e.to_string());
+        assert_eq!(
+            error("Card 1 41 48 | 83 86"),
+            Err("line 1, column 1: expected `:`".to_string())
+        );
+        assert_eq!(
+            error("Card 1: 41 48 83 86"),
+            Err("line 1, column 9: expected `|`".to_string())
+        );
+        assert_eq!(
+            error("Card x: 41 | 83"),
+            Err("line 1, column 6: invalid card id `x`".to_string())
+        );
+        assert_eq!(
+            error("Card:1 41 | 83"),
+            Err("line 1, column 5: invalid card id ``".to_string())
+        );
+        assert_eq!(
+            error("Card 1: 41 | 300"),
+            Err("line 1, column 14: invalid number `300`".to_string())
+        );
+        assert_eq!(
+            parse_cards("Card 1: 41 | 83\n\nCard 2: 41 | 83").map_err(|e| e.to_string()),
+            Err("line 2, column 1: expected `:`".to_string())
+        );
     }
 }
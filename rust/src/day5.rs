@@ -1,94 +1,574 @@
-use std::fs;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use crate::parse::{ErrorKind, ParseError, Text};
 use crate::puzzle::AocPuzzle;
 
-pub fn day_5(puzzle: AocPuzzle) -> u64 {
-    let puzzle_file = get_puzzle();
+pub fn solve(input: &str, puzzle: AocPuzzle) -> Result<u64, ParseError> {
     match puzzle {
-        AocPuzzle::PartOne => part_1(&puzzle_file),
-        AocPuzzle::PartTwo => part_2(&puzzle_file),
+        AocPuzzle::PartOne => part_1(input),
+        AocPuzzle::PartTwo => part_2(input),
     }
 }
 
-fn get_puzzle() -> String {
-    fs::read_to_string("puzzle_5_1").expect("Should have been able to read the file")
+pub fn compose(input: &str, from: &str, to: &str) -> Result<Option<String>, ParseError> {
+    Ok(Almanac::from_raw_str(input)?
+        .compose(from, to)
+        .map(|m| m.to_string()))
 }
 
-fn part_1(input: &str) -> u64 {
-    Almanac::from_raw_str(input).run()
+pub fn convert(input: &str, from: &str, n: u64, to: &str) -> Result<Option<u64>, ParseError> {
+    Ok(Almanac::from_raw_str(input)?.lookup(from, to, n))
 }
 
-fn part_2(input: &str) -> u64 {
-    unimplemented!()
+// The reverse direction, e.g. every seed that ends up at a location
+pub fn preimage(input: &str, from: &str, n: u64, to: &str) -> Result<Option<Vec<u64>>, ParseError> {
+    Ok(Almanac::from_raw_str(input)?.preimage(to, from, n))
+}
+
+// e.g. the seed ranges that end up in a range of locations
+pub fn convert_back(
+    input: &str,
+    from: &str,
+    range: Range<u64>,
+    to: &str,
+) -> Result<Option<Vec<Range<u64>>>, ParseError> {
+    Ok(Almanac::from_raw_str(input)?.preimage_range(to, from, range))
+}
+
+pub fn trace(input: &str, seed: u64) -> Result<Option<Vec<String>>, ParseError> {
+    let almanac = Almanac::from_raw_str(input)?;
+    Ok(almanac
+        .trace("seed", "location", seed)
+        .map(|steps| steps.iter().map(|s| s.to_string()).collect()))
+}
+
+// Prints progress to stderr, and the answers of both strategies to stdout
+pub fn brute_force(input: &str, threads: usize) -> Result<bool, ParseError> {
+    let almanac = Almanac::from_raw_str(input)?;
+    let seeds = almanac.seed_ranges();
+    let total: u64 = seeds.iter().map(|r| r.end - r.start).sum();
+    let done = AtomicU64::new(0);
+    let finished = AtomicBool::new(false);
+    let start = Instant::now();
+
+    let brute_force = thread::scope(|scope| {
+        scope.spawn(|| {
+            while !finished.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(500));
+                let done = done.load(Ordering::Relaxed);
+                eprint!(
+                    "\r{done}/{total} seeds ({:.1}%), {:.0} seeds/s",
+                    done as f64 * 100.0 / total as f64,
+                    done as f64 / start.elapsed().as_secs_f64()
+                );
+            }
+            eprintln!();
+        });
+        let res = almanac.brute_force(&seeds, threads, &done);
+        finished.store(true, Ordering::Relaxed);
+        res
+    });
+
+    let elapsed = start.elapsed();
+    let ranges = almanac.lowest_location(&seeds);
+    println!(
+        "{total} seeds on {threads} threads in {elapsed:?} ({:.0} seeds/s)",
+        total as f64 / elapsed.as_secs_f64()
+    );
+    println!("brute force: {brute_force:?}");
+    println!("ranges: {ranges:?}");
+    Ok(brute_force == ranges)
+}
+
+pub fn lint(input: &str) -> Vec<String> {
+    match Almanac::from_raw_str(input) {
+        Ok(almanac) => almanac
+            .maps
+            .iter()
+            .flat_map(|m| {
+                m.lint()
+                    .into_iter()
+                    .map(move |issue| format!("{}-to-{}: {issue}", m.source, m.destination))
+            })
+            .collect(),
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+// The almanac parsed, but there is nothing to answer with
+fn unanswerable(input: &str) -> ParseError {
+    Text::new(input).expected("seeds and maps from seed to location")
+}
+
+fn part_1(input: &str) -> Result<u64, ParseError> {
+    Almanac::from_raw_str(input)?
+        .run()
+        .ok_or_else(|| unanswerable(input))
+}
+
+fn part_2(input: &str) -> Result<u64, ParseError> {
+    let almanac = Almanac::from_raw_str(input)?;
+    almanac
+        .lowest_location(&almanac.seed_ranges())
+        .ok_or_else(|| unanswerable(input))
 }
 
 #[derive(PartialEq, Debug)]
-struct Almanac {
+pub(crate) struct Almanac {
     seeds: Vec<u64>,
     maps: Vec<Map>,
 }
 
 impl Almanac {
-    fn from_raw_str(input: &str) -> Almanac {
-        let mut a = Almanac {
-            seeds: vec![],
-            maps: vec![],
-        };
+    // `seeds: ...` first, then one block per map
+    pub(crate) fn from_raw_str(input: &str) -> Result<Almanac, ParseError> {
+        let input = Text::new(input);
+        let (preamble, blocks) = input.blocks();
 
-        let mut fns_acc: Vec<Fn> = vec![];
+        let mut seeds = None;
+        for line in preamble {
+            let raw_seeds = line
+                .strip_prefix("seeds:")
+                .map_err(|_| line.expected("`seeds:` or a map header"))?;
+            seeds = Some(raw_seeds.numbers()?);
+        }
 
-        for line in input.lines() {
-            if line.starts_with("seeds") {
-                a.seeds = line.split_whitespace().flat_map(|x| x.parse()).collect();
-                continue;
-            }
+        let maps = blocks
+            .into_iter()
+            .map(|block| {
+                let name = block.label.strip_suffix("map")?.trim();
+                let (source, destination) = name.split_once("-to-")?;
+                if source.is_empty() || destination.is_empty() {
+                    return Err(
+                        name.error(ErrorKind::Invalid("map header", name.as_str().to_string()))
+                    );
+                }
+                let fns = block
+                    .lines
+                    .into_iter()
+                    .map(|line| match line.numbers()?[..] {
+                        [to, from, size] => match Fn::from(to, from, size) {
+                            f if f.overflows() => Err(line.error(ErrorKind::Overflow("range"))),
+                            f => Ok(f),
+                        },
+                        _ => Err(line.expected("`destination source size`")),
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Map::new(source.as_str(), destination.as_str(), fns))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Almanac {
+            seeds: seeds.ok_or_else(|| input.expected("a `seeds:` line"))?,
+            maps,
+        })
+    }
 
-            if line.contains("map:") {
-                if &fns_acc.len() > &0 {
-                    a.maps.push(Map {
-                        // dirty
-                        fns: fns_acc.clone(),
-                    });
+    // Breadth-first search over categories, following maps from source to destination
+    fn path(&self, from: &str, to: &str) -> Option<Vec<&Map>> {
+        let mut previous: HashMap<&str, &Map> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(category) = queue.pop_front() {
+            if category == to {
+                let mut path = vec![];
+                let mut current = to;
+                while current != from {
+                    let map = previous[current];
+                    path.push(map);
+                    current = &map.source;
                 }
-                fns_acc.clear();
-                continue;
+                path.reverse();
+                return Some(path);
             }
 
-            let nums: Vec<u64> = line.split_whitespace().flat_map(|x| x.parse()).collect();
-            if nums.len() > 0 {
-                let to = nums.get(0).expect("Weird 0");
-                let from = nums.get(1).expect("Weird 1");
-                let size = nums.get(2).expect("Weird 2");
-                fns_acc.push(Fn::from(*to, *from, *size));
+            for map in self.maps.iter().filter(|m| m.source == category) {
+                let next = map.destination.as_str();
+                if next != from && !previous.contains_key(next) {
+                    previous.insert(next, map);
+                    queue.push_back(next);
+                }
             }
         }
+        None
+    }
 
-        a.maps.push(Map {
-            fns: fns_acc.clone(),
-        });
+    fn lookup(&self, from: &str, to: &str, n: u64) -> Option<u64> {
+        let path = self.path(from, to)?;
+        Some(path.iter().fold(n, |acc, m| m.get(acc)))
+    }
+
+    fn compose(&self, from: &str, to: &str) -> Option<Map> {
+        let path = self.path(from, to)?;
+        Some(
+            path.iter()
+                .fold(Map::new(from, from, vec![]), |acc, m| acc.compose(m)),
+        )
+    }
 
-        a
+    // All the values of `from` that end up as `n` in `to`
+    fn preimage(&self, from: &str, to: &str, n: u64) -> Option<Vec<u64>> {
+        let path = self.path(from, to)?;
+        Some(path.iter().rev().fold(vec![n], |acc, m| {
+            let mut values: Vec<u64> = acc.iter().flat_map(|v| m.preimage(*v)).collect();
+            values.sort_unstable();
+            values.dedup();
+            values
+        }))
     }
-    fn run(&self) -> u64 {
+
+    fn preimage_range(&self, from: &str, to: &str, range: Range<u64>) -> Option<Vec<Range<u64>>> {
+        Some(self.compose(from, to)?.preimage_range(range))
+    }
+
+    fn seed_ranges(&self) -> Vec<Range<u64>> {
         self.seeds
+            .chunks(2)
+            .map(|c| c[0]..c[0].saturating_add(*c.get(1).unwrap_or(&1)))
+            .collect()
+    }
+
+    // Walks location ranges upwards and stops once they can't beat the best one found
+    fn lowest_location(&self, seeds: &[Range<u64>]) -> Option<u64> {
+        let map = self.compose("seed", "location")?;
+        let mut pieces = map.inverse_pieces();
+        pieces.sort_unstable_by_key(|(image, _)| image.start);
+
+        let mut best: Option<u64> = None;
+        for (image, source) in pieces {
+            if best.is_some_and(|b| image.start >= b) {
+                break;
+            }
+            for seed_range in seeds {
+                let start = source.start.max(seed_range.start);
+                if start < source.end.min(seed_range.end) {
+                    let location = image.start + (start - source.start);
+                    best = Some(best.map_or(location, |b| b.min(location)));
+                }
+            }
+        }
+        best
+    }
+
+    fn trace(&self, from: &str, to: &str, n: u64) -> Option<Vec<Step<'_>>> {
+        let mut input = n;
+        let steps = self
+            .path(from, to)?
+            .into_iter()
+            .map(|map| {
+                let output = map.get(input);
+                let step = Step {
+                    map,
+                    input,
+                    output,
+                    matched: map.fns.iter().find(|f| f.contains(input)).copied(),
+                };
+                input = output;
+                step
+            })
+            .collect();
+        Some(steps)
+    }
+
+    // Evaluates every seed through `Map::get`, as a reference for `lowest_location`.
+    // `done` counts the evaluated seeds so that progress can be followed from outside.
+    fn brute_force(&self, seeds: &[Range<u64>], threads: usize, done: &AtomicU64) -> Option<u64> {
+        const CHUNK: u64 = 1 << 16;
+        let path = self.path("seed", "location")?;
+        let chunks: Vec<Range<u64>> = seeds
             .iter()
-            .map(|s| {
-                let r = self.maps.iter().fold(*s, |acc, m| m.get(acc));
-                r
+            .flat_map(|r| {
+                (r.start..r.end)
+                    .step_by(CHUNK as usize)
+                    .map(move |start| start..(start.saturating_add(CHUNK)).min(r.end))
             })
-            .min()
-            .unwrap()
+            .collect();
+        let next = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut best: Option<u64> = None;
+                        while let Some(chunk) = chunks.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            let min = chunk
+                                .clone()
+                                .map(|seed| path.iter().fold(seed, |acc, m| m.get(acc)))
+                                .min();
+                            best = best.into_iter().chain(min).min();
+                            done.fetch_add(chunk.end - chunk.start, Ordering::Relaxed);
+                        }
+                        best
+                    })
+                })
+                .collect();
+            workers.into_iter().filter_map(|w| w.join().unwrap()).min()
+        })
+    }
+
+    fn run(&self) -> Option<u64> {
+        let map = self.compose("seed", "location")?;
+        let mut seeds = self.seeds.clone();
+        seeds.sort_unstable();
+        map.get_sorted(&seeds).into_iter().min()
+    }
+}
+
+struct Step<'a> {
+    map: &'a Map,
+    input: u64,
+    output: u64,
+    matched: Option<Fn>,
+}
+
+impl fmt::Display for Step<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} -> {} {} ",
+            self.map.source, self.input, self.map.destination, self.output
+        )?;
+        match self.matched {
+            Some(matched) => write!(f, "({matched})"),
+            None => write!(f, "(identity)"),
+        }
     }
 }
 
 #[derive(PartialEq, Debug)]
 struct Map {
+    source: String,
+    destination: String,
     fns: Vec<Fn>,
+    // Sorted by source, disjoint and without identity ranges
+    index: Vec<Fn>,
+}
+
+fn push_merged(fns: &mut Vec<Fn>, f: Fn) {
+    if f.to == f.from {
+        return;
+    }
+    match fns.last_mut() {
+        Some(last) if last.end() == f.from && last.to.checked_add(last.size) == Some(f.to) => {
+            last.size += f.size
+        }
+        _ => fns.push(f),
+    }
+}
+
+// Sweeps over the range boundaries, keeping the ranges that cover the current
+// position. When they overlap, the first one in the file wins, like `find_map` would.
+fn build_index(fns: &[Fn]) -> Vec<Fn> {
+    let ranges: Vec<(usize, &Fn)> = fns.iter().enumerate().filter(|(_, f)| f.size > 0).collect();
+    let mut starts = ranges.clone();
+    starts.sort_unstable_by_key(|(_, f)| f.from);
+    let mut ends = ranges;
+    ends.sort_unstable_by_key(|(_, f)| f.end());
+
+    let mut breakpoints: Vec<u64> = fns.iter().flat_map(|f| [f.from, f.end()]).collect();
+    breakpoints.sort_unstable();
+    breakpoints.dedup();
+
+    let mut active = BTreeSet::new();
+    let (mut s, mut e) = (0, 0);
+    let mut index = vec![];
+    for w in breakpoints.windows(2) {
+        let (start, end) = (w[0], w[1]);
+        while s < starts.len() && starts[s].1.from <= start {
+            active.insert(starts[s].0);
+            s += 1;
+        }
+        while e < ends.len() && ends[e].1.end() <= start {
+            active.remove(&ends[e].0);
+            e += 1;
+        }
+        if let Some(&i) = active.first() {
+            let to = fns[i].get(start).unwrap();
+            push_merged(&mut index, Fn::from(to, start, end - start));
+        }
+    }
+    index
 }
 
 impl Map {
+    fn new(source: &str, destination: &str, fns: Vec<Fn>) -> Map {
+        Map {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            index: build_index(&fns),
+            fns,
+        }
+    }
+
     fn get(&self, n: u64) -> u64 {
-        self.fns.iter().find_map(|v| v.get(n)).unwrap_or(n)
+        let i = self.index.partition_point(|f| f.from <= n);
+        i.checked_sub(1)
+            .and_then(|i| self.index[i].get(n))
+            .unwrap_or(n)
+    }
+
+    // `values` must be sorted, the index is walked only once
+    fn get_sorted(&self, values: &[u64]) -> Vec<u64> {
+        let mut i = 0;
+        values
+            .iter()
+            .map(|&n| {
+                while i < self.index.len() && self.index[i].end() <= n {
+                    i += 1;
+                }
+                self.index.get(i).and_then(|f| f.get(n)).unwrap_or(n)
+            })
+            .collect()
+    }
+
+    // `next` applied after `self`.
+    // Between two consecutive breakpoints, both maps are a plain offset.
+    fn compose(&self, next: &Map) -> Map {
+        let mut breakpoints: Vec<u64> = vec![0];
+        for f in &self.index {
+            breakpoints.push(f.from);
+            breakpoints.push(f.end());
+        }
+        for g in &next.index {
+            for y in [g.from, g.end()] {
+                breakpoints.push(y);
+                breakpoints.extend(
+                    self.index
+                        .iter()
+                        .filter(|f| f.image_contains(y))
+                        .map(|f| f.from + (y - f.to)),
+                );
+            }
+        }
+        breakpoints.sort_unstable();
+        breakpoints.dedup();
+
+        let mut fns: Vec<Fn> = vec![];
+        for w in breakpoints.windows(2) {
+            let (start, end) = (w[0], w[1]);
+            let to = next.get(self.get(start));
+            push_merged(&mut fns, Fn::from(to, start, end - start));
+        }
+        Map::new(&self.source, &next.destination, fns)
+    }
+
+    fn preimage(&self, n: u64) -> Vec<u64> {
+        let mut values: Vec<u64> = self
+            .index
+            .iter()
+            .filter(|f| f.image_contains(n))
+            .map(|f| f.from + (n - f.to))
+            .collect();
+        if self.get(n) == n {
+            values.push(n);
+        }
+        values.sort_unstable();
+        values.dedup();
+        values
+    }
+
+    // (image, source) pairs covering the whole source space, identity gaps included
+    fn inverse_pieces(&self) -> Vec<(Range<u64>, Range<u64>)> {
+        let mut pieces = vec![];
+        let mut gap_start = 0;
+        for f in &self.index {
+            if gap_start < f.from {
+                pieces.push((gap_start..f.from, gap_start..f.from));
+            }
+            pieces.push((f.to..f.to.saturating_add(f.size), f.from..f.end()));
+            gap_start = f.end();
+        }
+        if gap_start < u64::MAX {
+            pieces.push((gap_start..u64::MAX, gap_start..u64::MAX));
+        }
+        pieces
+    }
+
+    // Source ranges whose values end up in `range`, sorted and merged
+    fn preimage_range(&self, range: Range<u64>) -> Vec<Range<u64>> {
+        let mut ranges: Vec<Range<u64>> = self
+            .inverse_pieces()
+            .into_iter()
+            .filter_map(|(image, source)| {
+                let start = image.start.max(range.start);
+                let end = image.end.min(range.end);
+                (start < end).then(|| {
+                    source.start + (start - image.start)..source.start + (end - image.start)
+                })
+            })
+            .collect();
+        ranges.sort_unstable_by_key(|r| r.start);
+
+        let mut merged: Vec<Range<u64>> = vec![];
+        for r in ranges {
+            match merged.last_mut() {
+                Some(last) if last.end >= r.start => last.end = last.end.max(r.end),
+                _ => merged.push(r),
+            }
+        }
+        merged
+    }
+
+    fn lint(&self) -> Vec<Issue> {
+        let mut issues = vec![];
+        for f in &self.fns {
+            if f.size == 0 {
+                issues.push(Issue::ZeroSize(*f));
+            } else if f.overflows() {
+                issues.push(Issue::Overflow(*f));
+            }
+        }
+
+        let fns: Vec<&Fn> = self.fns.iter().filter(|f| f.size > 0).collect();
+        for (i, a) in fns.iter().enumerate() {
+            for b in &fns[i + 1..] {
+                if a.from.max(b.from) - a.from < a.size && a.from.max(b.from) - b.from < b.size {
+                    issues.push(Issue::OverlappingSources(**a, **b));
+                }
+                if a.to.max(b.to) - a.to < a.size && a.to.max(b.to) - b.to < b.size {
+                    issues.push(Issue::OverlappingDestinations(**a, **b));
+                }
+            }
+        }
+        issues
+    }
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}-to-{} map:", self.source, self.destination)?;
+        for function in &self.fns {
+            writeln!(f, "{function}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Debug)]
+enum Issue {
+    ZeroSize(Fn),
+    Overflow(Fn),
+    OverlappingSources(Fn, Fn),
+    OverlappingDestinations(Fn, Fn),
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::ZeroSize(a) => write!(f, "`{a}` has a size of 0"),
+            Issue::Overflow(a) => write!(f, "`{a}` doesn't fit below {}", u64::MAX),
+            Issue::OverlappingSources(a, b) => {
+                write!(f, "`{a}` and `{b}` overlap on their sources")
+            }
+            Issue::OverlappingDestinations(a, b) => {
+                write!(f, "`{a}` and `{b}` overlap on their destinations")
+            }
+        }
     }
 }
 
@@ -101,8 +581,22 @@ struct Fn {
 
 impl Fn {
     fn contains(&self, n: u64) -> bool {
-        n >= self.from && n < self.from + self.size
+        n >= self.from && n - self.from < self.size
+    }
+
+    fn image_contains(&self, n: u64) -> bool {
+        n >= self.to && n - self.to < self.size
     }
+
+    fn end(&self) -> u64 {
+        self.from.saturating_add(self.size)
+    }
+
+    // Ends are exclusive, so u64::MAX itself can't be part of a range
+    fn overflows(&self) -> bool {
+        self.from.checked_add(self.size).is_none() || self.to.checked_add(self.size).is_none()
+    }
+
     fn get(&self, n: u64) -> Option<u64> {
         if self.contains(n) {
             let index = n - self.from;
@@ -116,137 +610,98 @@ impl Fn {
     }
 }
 
+impl fmt::Display for Fn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.to, self.from, self.size)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Almanac, Fn, Map};
+    use std::hint::black_box;
+    use std::ops::Range;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use crate::bench::measure;
+    use crate::generate::{generate, Options};
+    use crate::rng::Rng;
+
+    use super::{Almanac, Fn, Issue, Map};
 
     #[test]
     fn part_1() {
         assert_eq!(
-            Almanac::from_raw_str(
-                "seeds: 79 14 55 13
-
-        seed-to-soil map:
-        50 98 2
-        52 50 48
-        
-        soil-to-fertilizer map:
-        0 15 37
-        37 52 2
-        39 0 15
-        
-        fertilizer-to-water map:
-        49 53 8
-        0 11 42
-        42 0 7
-        57 7 4
-        
-        water-to-light map:
-        88 18 7
-        18 25 70
-        
-        light-to-temperature map:
-        45 77 23
-        81 45 19
-        68 64 13
-        
-        temperature-to-humidity map:
-        0 69 1
-        1 0 69
-        
-        humidity-to-location map:
-        60 56 37
-        56 93 4"
-            )
-            .run(),
-            35
+            Almanac::from_raw_str(include_str!("../examples/day5/example.txt"))
+                .unwrap()
+                .run(),
+            Some(35)
         );
     }
 
     #[test]
     fn parse_input() {
         assert_eq!(
-            Almanac::from_raw_str(
-                "seeds: 79 14 55 13
-
-        seed-to-soil map:
-        50 98 2
-        52 50 48
-        
-        soil-to-fertilizer map:
-        0 15 37
-        37 52 2
-        39 0 15
-        
-        fertilizer-to-water map:
-        49 53 8
-        0 11 42
-        42 0 7
-        57 7 4
-        
-        water-to-light map:
-        88 18 7
-        18 25 70
-        
-        light-to-temperature map:
-        45 77 23
-        81 45 19
-        68 64 13
-        
-        temperature-to-humidity map:
-        0 69 1
-        1 0 69
-        
-        humidity-to-location map:
-        60 56 37
-        56 93 4"
-            ),
-            Almanac {
+            Almanac::from_raw_str(include_str!("../examples/day5/example.txt")),
+            Ok(Almanac {
                 seeds: vec![79, 14, 55, 13],
                 maps: vec![
                     // Seed to soil
-                    Map {
-                        fns: vec![Fn::from(50, 98, 2), Fn::from(52, 50, 48)],
-                    },
+                    Map::new(
+                        "seed",
+                        "soil",
+                        vec![Fn::from(50, 98, 2), Fn::from(52, 50, 48)]
+                    ),
                     // Soil to fertilizer
-                    Map {
-                        fns: vec![
+                    Map::new(
+                        "soil",
+                        "fertilizer",
+                        vec![
                             Fn::from(0, 15, 37),
                             Fn::from(37, 52, 2),
                             Fn::from(39, 0, 15)
-                        ],
-                    },
+                        ]
+                    ),
                     // Fertilizer-to-water
-                    Map {
-                        fns: vec![
+                    Map::new(
+                        "fertilizer",
+                        "water",
+                        vec![
                             Fn::from(49, 53, 8),
                             Fn::from(0, 11, 42),
                             Fn::from(42, 0, 7),
                             Fn::from(57, 7, 4)
                         ]
-                    },
+                    ),
                     // Water to light
-                    Map {
-                        fns: vec![Fn::from(88, 18, 7), Fn::from(18, 25, 70)]
-                    },
+                    Map::new(
+                        "water",
+                        "light",
+                        vec![Fn::from(88, 18, 7), Fn::from(18, 25, 70)]
+                    ),
                     // Light to temperature
-                    Map {
-                        fns: vec![
+                    Map::new(
+                        "light",
+                        "temperature",
+                        vec![
                             Fn::from(45, 77, 23),
                             Fn::from(81, 45, 19),
                             Fn::from(68, 64, 13)
                         ]
-                    },
+                    ),
                     // temperatuer to humidity
-                    Map {
-                        fns: vec![Fn::from(0, 69, 1), Fn::from(1, 0, 69)]
-                    },
+                    Map::new(
+                        "temperature",
+                        "humidity",
+                        vec![Fn::from(0, 69, 1), Fn::from(1, 0, 69)]
+                    ),
                     // humidity to location,
-                    Map {
-                        fns: vec![Fn::from(60, 56, 37), Fn::from(56, 93, 4)]
-                    }
+                    Map::new(
+                        "humidity",
+                        "location",
+                        vec![Fn::from(60, 56, 37), Fn::from(56, 93, 4)]
+                    )
                 ],
-            }
+            })
         )
     }
 
@@ -265,8 +720,10 @@ mod tests {
 
     #[test]
     fn test_get_map() {
-        let m1 = Map {
-            fns: vec![
+        let m1 = Map::new(
+            "seed",
+            "soil",
+            vec![
                 Fn {
                     from: 98,
                     to: 50,
@@ -278,7 +735,7 @@ mod tests {
                     size: 48,
                 },
             ],
-        };
+        );
 
         assert_eq!(m1.get(79), 81);
         assert_eq!(m1.get(14), 14);
@@ -291,8 +748,10 @@ mod tests {
         let a = Almanac {
             seeds: vec![79, 14, 55, 13],
             maps: vec![
-                Map {
-                    fns: vec![
+                Map::new(
+                    "seed",
+                    "soil",
+                    vec![
                         Fn {
                             from: 98,
                             to: 50,
@@ -304,9 +763,11 @@ mod tests {
                             size: 48,
                         },
                     ],
-                },
-                Map {
-                    fns: vec![
+                ),
+                Map::new(
+                    "soil",
+                    "location",
+                    vec![
                         Fn {
                             from: 15,
                             to: 0,
@@ -323,9 +784,475 @@ mod tests {
                             size: 15,
                         },
                     ],
-                },
+                ),
             ],
         };
-        assert_eq!(a.run(), 52);
+        assert_eq!(a.run(), Some(52));
+    }
+
+    #[test]
+    fn parse_keeps_empty_maps() {
+        let a = Almanac::from_raw_str(
+            "seeds: 1 2
+
+seed-to-soil map:
+
+soil-to-fertilizer map:
+10 0 5
+",
+        )
+        .unwrap();
+        assert_eq!(
+            a.maps,
+            vec![
+                Map::new("seed", "soil", vec![]),
+                Map::new("soil", "fertilizer", vec![Fn::from(10, 0, 5)])
+            ]
+        );
+        assert_eq!(a.lookup("seed", "fertilizer", 1), Some(11));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |s| Almanac::from_raw_str(s).map_err(|e| e.to_string());
+        assert_eq!(
+            error("seed-to-soil map:\n50 98 2"),
+            Err("line 1, column 1: expected a `seeds:` line".to_string())
+        );
+        assert_eq!(
+            error("seeds: 1 x"),
+            Err("line 1, column 10: invalid number `x`".to_string())
+        );
+        assert_eq!(
+            error("seeds: 1\n\nseed-soil map:"),
+            Err("line 3, column 1: expected `-to-`".to_string())
+        );
+        assert_eq!(
+            error("seeds: 1\n\n-to-soil map:"),
+            Err("line 3, column 1: invalid map header `-to-soil`".to_string())
+        );
+        assert_eq!(
+            error("seeds: 1\n50 98 2"),
+            Err("line 2, column 1: expected `seeds:` or a map header".to_string())
+        );
+        assert_eq!(
+            error("seeds: 1\nseed-to-soil map:\n50 98\n"),
+            Err("line 3, column 1: expected `destination source size`".to_string())
+        );
+        assert_eq!(
+            error("seeds: 1\n\nseed-to-soil map:\n  50 98 2 1"),
+            Err("line 4, column 3: expected `destination source size`".to_string())
+        );
+        assert_eq!(
+            error("seeds: 1\n\nseed-to-soil map:\n18446744073709551615 0 10"),
+            Err("line 4, column 1: range overflows a u64".to_string())
+        );
+        assert_eq!(
+            error("seeds: 1\n\nseed-to-soil map:\n0 18446744073709551615 1"),
+            Err("line 4, column 1: range overflows a u64".to_string())
+        );
+        // The last value that a range can hold
+        let almanac = Almanac::from_raw_str(
+            "seeds: 18446744073709551614\n\nseed-to-location map:\n0 18446744073709551614 1",
+        );
+        assert_eq!(almanac.unwrap().run(), Some(0));
+    }
+
+    #[test]
+    fn lookup_between_categories() {
+        let a = Almanac::from_raw_str(
+            "seeds: 79 14 55 13
+
+humidity-to-location map:
+60 56 37
+56 93 4
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69",
+        )
+        .unwrap();
+
+        assert_eq!(a.lookup("seed", "humidity", 79), Some(78));
+        assert_eq!(a.lookup("seed", "location", 79), Some(82));
+        assert_eq!(a.lookup("fertilizer", "location", 12), Some(2));
+        assert_eq!(a.lookup("light", "light", 74), Some(74));
+        assert_eq!(a.lookup("location", "seed", 82), None);
+        assert_eq!(a.lookup("seed", "sunlight", 79), None);
+        assert_eq!(a.run(), Some(35));
+    }
+
+    #[test]
+    fn compose_maps() {
+        let a = Almanac::from_raw_str(include_str!("../puzzle_5_1")).unwrap();
+        let composed = a.compose("seed", "location").unwrap();
+
+        assert!(composed
+            .fns
+            .windows(2)
+            .all(|w| w[0].from + w[0].size <= w[1].from));
+        assert!(composed.fns.iter().all(|f| f.from != f.to));
+
+        for seed in a.seeds.iter().flat_map(|s| [0, 1, *s, s + 1_000]) {
+            assert_eq!(
+                composed.get(seed),
+                a.lookup("seed", "location", seed).unwrap()
+            );
+        }
+        for f in &composed.fns {
+            for n in [f.from, f.from + f.size - 1, f.from + f.size] {
+                assert_eq!(composed.get(n), a.lookup("seed", "location", n).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn normalize_map() {
+        // Overlapping ranges: the first one in the file wins
+        let m = Map::new(
+            "seed",
+            "soil",
+            vec![
+                Fn::from(100, 10, 10),
+                Fn::from(200, 5, 10),
+                Fn::from(20, 20, 5),
+            ],
+        );
+        let normalized = m.compose(&Map::new("soil", "soil", vec![]));
+        assert_eq!(
+            normalized,
+            Map::new(
+                "seed",
+                "soil",
+                vec![Fn::from(200, 5, 5), Fn::from(100, 10, 10)]
+            )
+        );
+        for n in 0..30 {
+            assert_eq!(normalized.get(n), m.get(n));
+        }
+
+        let printed = normalized.to_string();
+        assert_eq!(printed, "seed-to-soil map:\n200 5 5\n100 10 10\n");
+        let reparsed = Almanac::from_raw_str(&format!("seeds: 1\n{printed}")).unwrap();
+        assert_eq!(reparsed.maps, vec![normalized]);
+    }
+
+    #[test]
+    fn preimage_of_map() {
+        let m = Map::new(
+            "seed",
+            "soil",
+            vec![Fn::from(50, 98, 2), Fn::from(52, 50, 48)],
+        );
+        assert_eq!(m.preimage(50), vec![98]);
+        assert_eq!(m.preimage(10), vec![10]);
+        assert_eq!(m.preimage(99), vec![97]);
+
+        // Not injective: 12 comes from 2 and from itself
+        let m = Map::new("seed", "soil", vec![Fn::from(10, 0, 5)]);
+        assert_eq!(m.preimage(12), vec![2, 12]);
+        assert_eq!(m.preimage(2), Vec::<u64>::new());
+
+        assert_eq!(m.preimage_range(11..13), vec![1..3, 11..13]);
+        for n in 0..20 {
+            let expected: Vec<u64> = (0..30).filter(|x| m.get(*x) == n).collect();
+            let from_ranges: Vec<u64> = m.preimage_range(n..n + 1).into_iter().flatten().collect();
+            assert_eq!(from_ranges, expected);
+            assert_eq!(m.preimage(n), expected);
+        }
+    }
+
+    #[test]
+    fn seeds_from_location() {
+        let a = Almanac::from_raw_str(include_str!("../puzzle_5_1")).unwrap();
+        for seed in &a.seeds {
+            let location = a.lookup("seed", "location", *seed).unwrap();
+            assert!(a
+                .preimage("seed", "location", location)
+                .unwrap()
+                .contains(seed));
+
+            let ranges = a
+                .preimage_range("seed", "location", location..location + 1)
+                .unwrap();
+            assert!(ranges.iter().any(|r| r.contains(seed)));
+        }
+    }
+
+    #[test]
+    fn part_2() {
+        assert_eq!(
+            super::part_2(include_str!("../examples/day5/example.txt")),
+            Ok(46)
+        );
+        // Seeds up to u64::MAX - 1, the last of them mapped to 0
+        assert_eq!(
+            super::part_2(
+                "seeds: 18446744073709551610 100\n\nseed-to-location map:\n0 18446744073709551614 1"
+            ),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn lint_maps() {
+        let m = Map::new(
+            "seed",
+            "soil",
+            vec![
+                Fn::from(50, 98, 2),
+                Fn::from(51, 50, 48),
+                Fn::from(0, 99, 5),
+                Fn::from(10, 10, 0),
+                Fn::from(u64::MAX, 200, 2),
+            ],
+        );
+        assert_eq!(
+            m.lint(),
+            vec![
+                Issue::ZeroSize(Fn::from(10, 10, 0)),
+                Issue::Overflow(Fn::from(u64::MAX, 200, 2)),
+                Issue::OverlappingDestinations(Fn::from(50, 98, 2), Fn::from(51, 50, 48)),
+                Issue::OverlappingSources(Fn::from(50, 98, 2), Fn::from(0, 99, 5)),
+            ]
+        );
+        assert_eq!(
+            Issue::OverlappingSources(Fn::from(50, 98, 2), Fn::from(0, 99, 5)).to_string(),
+            "`50 98 2` and `0 99 5` overlap on their sources"
+        );
+
+        // Touching ranges don't overlap
+        let m = Map::new(
+            "seed",
+            "soil",
+            vec![Fn::from(0, 0, 10), Fn::from(10, 10, 10)],
+        );
+        assert_eq!(m.lint(), vec![]);
+        assert_eq!(
+            Map::new("seed", "soil", vec![Fn::from(0, u64::MAX - 1, 1)]).lint(),
+            vec![]
+        );
+        assert_eq!(
+            Map::new("seed", "soil", vec![Fn::from(0, u64::MAX, 1)]).lint(),
+            vec![Issue::Overflow(Fn::from(0, u64::MAX, 1))]
+        );
+    }
+
+    fn linear_get(m: &Map, n: u64) -> u64 {
+        m.fns.iter().find_map(|f| f.get(n)).unwrap_or(n)
+    }
+
+    #[test]
+    fn index_matches_linear_scan() {
+        let m = Map::new(
+            "seed",
+            "soil",
+            vec![
+                Fn::from(100, 10, 10),
+                Fn::from(200, 5, 30),
+                Fn::from(40, 40, 10),
+                Fn::from(7, 60, 0),
+                Fn::from(300, 45, 20),
+                Fn::from(0, 90, 5),
+            ],
+        );
+        assert_eq!(
+            m.index,
+            vec![
+                Fn::from(200, 5, 5),
+                Fn::from(100, 10, 10),
+                Fn::from(215, 20, 15),
+                Fn::from(305, 50, 15),
+                Fn::from(0, 90, 5)
+            ]
+        );
+
+        let values: Vec<u64> = (0..120).collect();
+        for n in &values {
+            assert_eq!(m.get(*n), linear_get(&m, *n));
+        }
+        assert_eq!(
+            m.get_sorted(&values),
+            values.iter().map(|n| m.get(*n)).collect::<Vec<_>>()
+        );
+    }
+
+    // cargo test --release bench_ -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_map_get() {
+        let fns: Vec<Fn> = (0..5_000)
+            .map(|i| Fn::from((i * 7_919) % 5_000 * 1_000, i * 1_000, 600))
+            .collect();
+        let m = Map::new("seed", "soil", fns);
+        let values: Vec<u64> = (0..100_000).map(|i| i * 50).collect();
+
+        let linear = measure("linear scan", 5, || {
+            values
+                .iter()
+                .map(|n| linear_get(black_box(&m), *n))
+                .sum::<u64>()
+        });
+        let indexed = measure("binary search", 5, || {
+            values.iter().map(|n| black_box(&m).get(*n)).sum::<u64>()
+        });
+        let batched = measure("sorted batch", 5, || {
+            black_box(&m).get_sorted(&values).iter().sum::<u64>()
+        });
+        assert_eq!(linear, indexed);
+        assert_eq!(linear, batched);
+    }
+
+    #[test]
+    fn trace_seed() {
+        let a = Almanac::from_raw_str(include_str!("../examples/day5/example.txt")).unwrap();
+
+        let lines: Vec<String> = a
+            .trace("seed", "location", 79)
+            .unwrap()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "seed 79 -> soil 81 (52 50 48)",
+                "soil 81 -> fertilizer 81 (identity)",
+                "fertilizer 81 -> water 81 (identity)",
+                "water 81 -> light 74 (18 25 70)",
+                "light 74 -> temperature 78 (68 64 13)",
+                "temperature 78 -> humidity 78 (identity)",
+                "humidity 78 -> location 82 (60 56 37)",
+            ]
+        );
+        assert!(a.trace("location", "seed", 82).is_none());
+    }
+
+    #[test]
+    fn brute_force_matches_ranges() {
+        let a = Almanac::from_raw_str(include_str!("../examples/day5/example.txt")).unwrap();
+
+        for seeds in [
+            a.seed_ranges(),
+            vec![0..500, 500..1_000],
+            vec![3..4, 90..200_000],
+        ] {
+            let done = AtomicU64::new(0);
+            assert_eq!(a.brute_force(&seeds, 4, &done), a.lowest_location(&seeds));
+            assert_eq!(
+                done.load(Ordering::Relaxed),
+                seeds.iter().map(|r| r.end - r.start).sum::<u64>()
+            );
+        }
+        assert_eq!(a.brute_force(&[], 2, &AtomicU64::new(0)), None);
+    }
+
+    // Small values so that ranges overlap, touch and leave gaps
+    fn random_map(rng: &mut Rng, source: &str, destination: &str) -> Map {
+        let fns = (0..rng.range(0..8))
+            .map(|_| Fn::from(rng.range(0..100), rng.range(0..100), rng.range(0..40)))
+            .collect();
+        Map::new(source, destination, fns)
+    }
+
+    fn expand(ranges: &[Range<u64>], within: Range<u64>) -> Vec<u64> {
+        within
+            .filter(|n| ranges.iter().any(|r| r.contains(n)))
+            .collect()
+    }
+
+    #[test]
+    fn maps_match_linear_scan() {
+        for seed in 0..500 {
+            let mut rng = Rng::new(seed);
+            let m = random_map(&mut rng, "seed", "soil");
+            for n in 0..200 {
+                assert_eq!(m.get(n), linear_get(&m, n), "seed {seed}, n {n}\n{m}");
+            }
+        }
+    }
+
+    #[test]
+    fn ranges_match_per_seed_get() {
+        for seed in 0..500 {
+            let mut rng = Rng::new(seed);
+            let m = random_map(&mut rng, "seed", "soil");
+            let next = random_map(&mut rng, "soil", "water");
+
+            let composed = m.compose(&next);
+            for n in 0..200 {
+                assert_eq!(composed.get(n), next.get(m.get(n)), "seed {seed}, n {n}");
+            }
+
+            let start = rng.range(0..150);
+            let range = start..start + rng.range(1..50);
+            assert_eq!(
+                expand(&m.preimage_range(range.clone()), 0..200),
+                (0..200)
+                    .filter(|&n| range.contains(&m.get(n)))
+                    .collect::<Vec<_>>(),
+                "seed {seed}, range {range:?}\n{m}"
+            );
+        }
+    }
+
+    #[test]
+    fn lowest_location_matches_per_seed_get() {
+        for seed in 0..100 {
+            let mut rng = Rng::new(seed);
+            let input = generate(
+                "day5",
+                &Options::from_strs(&[
+                    "--seed",
+                    &seed.to_string(),
+                    "--maps",
+                    &rng.range(1..8).to_string(),
+                    "--ranges",
+                    &rng.range(1..6).to_string(),
+                    "--seed-length",
+                    "50",
+                    "--max",
+                    "300",
+                ]),
+            );
+            let a = Almanac::from_raw_str(&input).unwrap();
+            let path = a.path("seed", "location").unwrap();
+            let location = |n: u64| path.iter().fold(n, |acc, m| m.get(acc));
+
+            assert_eq!(
+                a.run(),
+                a.seeds.iter().map(|&n| location(n)).min(),
+                "seed {seed}"
+            );
+            assert_eq!(
+                a.lowest_location(&a.seed_ranges()),
+                a.seed_ranges().into_iter().flatten().map(location).min(),
+                "seed {seed}"
+            );
+        }
     }
 }
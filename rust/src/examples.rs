@@ -0,0 +1,106 @@
+// Sample inputs live in `examples/<day>/<name>.txt`, with the expected answers
+// as `part1: <answer>` lines in the matching `<name>.answers` file.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::puzzle::{self, AocPuzzle};
+
+pub struct Example {
+    pub name: String,
+    pub input: String,
+    pub answers: Vec<(AocPuzzle, u64)>,
+}
+
+fn sorted_entries(dir: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| Some(e.ok()?.path())).collect(),
+        Err(_) => vec![],
+    };
+    entries.sort();
+    entries
+}
+
+fn parse_answers(s: &str) -> Option<Vec<(AocPuzzle, u64)>> {
+    s.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            let (part, answer) = l.split_once(':')?;
+            Some((
+                AocPuzzle::from_name(part.trim())?,
+                answer.trim().parse().ok()?,
+            ))
+        })
+        .collect()
+}
+
+pub fn examples_dir(root: &Path, day: &str) -> PathBuf {
+    root.join("examples").join(day)
+}
+
+pub fn load(root: &Path, day: &str) -> Result<Vec<Example>, String> {
+    sorted_entries(&examples_dir(root, day))
+        .iter()
+        .filter(|p| p.extension().is_some_and(|e| e == "txt"))
+        .map(|input_path| {
+            let answers_path = input_path.with_extension("answers");
+            let answers = fs::read_to_string(&answers_path)
+                .map_err(|_| format!("No answers for {}", input_path.display()))?;
+            Ok(Example {
+                name: input_path
+                    .file_stem()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+                input: puzzle::read_input(input_path)
+                    .map_err(|e| format!("{}: {e}", input_path.display()))?,
+                answers: parse_answers(&answers).ok_or_else(|| {
+                    format!(
+                        "{}: expected `part1: <answer>` lines",
+                        answers_path.display()
+                    )
+                })?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{load, sorted_entries};
+    use crate::solve;
+
+    #[test]
+    fn test_examples() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut checked = 0;
+        let mut failures = vec![];
+
+        for day_dir in sorted_entries(&root.join("examples"))
+            .iter()
+            .filter(|p| p.is_dir())
+        {
+            let day = day_dir.file_name().unwrap().to_str().unwrap();
+            for example in load(root, day).unwrap_or_else(|e| panic!("{e}")) {
+                for (puzzle, expected) in example.answers {
+                    match solve(day, puzzle, &example.input) {
+                        Ok(answer) if answer == expected => {}
+                        Ok(answer) => failures.push(format!(
+                            "{day}/{} {puzzle:?}: expected {expected}, got {answer}",
+                            example.name
+                        )),
+                        Err(e) => failures.push(format!(
+                            "{day}/{} {puzzle:?}: expected {expected}, but {e}",
+                            example.name
+                        )),
+                    }
+                    checked += 1;
+                }
+            }
+        }
+
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+        assert!(checked > 0, "No examples found in {}", root.display());
+    }
+}
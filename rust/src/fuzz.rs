@@ -0,0 +1,33 @@
+use crate::day1::Digits;
+use crate::parse::Text;
+use crate::puzzle::AocPuzzle;
+use crate::{day1, day2, day3, day4, day5};
+
+// Entry points for the targets in `fuzz/`: any input has to come out as an
+// error, never as a panic.
+
+pub fn day1_lines(input: &str) {
+    for digits in [Digits::Ascii, Digits::Unicode] {
+        for puzzle in [AocPuzzle::PartOne, AocPuzzle::PartTwo] {
+            let _ = day1::add_first_and_last_digit_of_text_lines(input, puzzle, digits);
+        }
+    }
+}
+
+pub fn day2_games(input: &str) {
+    for line in Text::new(input).lines() {
+        let _ = day2::Game::from_raw_str(line);
+    }
+}
+
+pub fn day3_tokens(input: &str) {
+    let _ = day3::tokenize_lines(input);
+}
+
+pub fn day4_cards(input: &str) {
+    let _ = day4::parse_cards(input);
+}
+
+pub fn day5_almanac(input: &str) {
+    let _ = day5::Almanac::from_raw_str(input);
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::rng::Rng;
+
+// `--key value` pairs from the command line
+pub struct Options {
+    values: HashMap<String, String>,
+}
+
+impl Options {
+    pub fn from_args(args: &[String]) -> Options {
+        let values = args
+            .chunks(2)
+            .map(|pair| {
+                let key = pair[0]
+                    .strip_prefix("--")
+                    .unwrap_or_else(|| panic!("Expected an option, got `{}`", pair[0]));
+                let value = pair
+                    .get(1)
+                    .unwrap_or_else(|| panic!("No value for `{}`", pair[0]));
+                (key.to_string(), value.to_string())
+            })
+            .collect();
+        Options { values }
+    }
+
+    #[cfg(test)]
+    pub fn from_strs(args: &[&str]) -> Options {
+        Options::from_args(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    fn get<T: FromStr>(&self, key: &str, default: T) -> T {
+        match self.values.get(key) {
+            Some(v) => v
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid value for --{key}: `{v}`")),
+            None => default,
+        }
+    }
+}
+
+pub fn generate(day: &str, options: &Options) -> String {
+    let mut rng = Rng::new(options.get("seed", 0));
+    match day {
+        "day1" => day1(
+            &mut rng,
+            options.get("lines", 1_000),
+            options.get("length", 20),
+            options.get("words", 0.2),
+        ),
+        "day2" => day2(
+            &mut rng,
+            options.get("games", 100),
+            options.get("sets", 6),
+            options.get("cubes", 20),
+        ),
+        "day3" => day3(
+            &mut rng,
+            options.get("width", 140),
+            options.get("height", 140),
+            options.get("density", 0.05),
+        ),
+        "day4" => day4(
+            &mut rng,
+            options.get("cards", 200),
+            options.get("winning", 10),
+            options.get("numbers", 25),
+            options.get("match-rate", 0.04),
+        ),
+        "day5" => day5(
+            &mut rng,
+            options.get("maps", 7),
+            options.get("ranges", 30),
+            options.get("seeds", 10),
+            options.get("seed-length", 1_000_000),
+            options.get("max", 1 << 32),
+        ),
+        _ => panic!("No generator for {day}"),
+    }
+}
+
+const NUMBER_WORDS: [&str; 9] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+
+// Letters, digits and number words, with at least one digit per line
+fn day1(rng: &mut Rng, lines: usize, length: usize, words: f64) -> String {
+    let mut out = String::new();
+    for _ in 0..lines {
+        let mut line = String::new();
+        let digit_at = rng.range(0..length.max(1) as u64) as usize;
+        for i in 0..length.max(1) {
+            if i == digit_at || rng.chance(0.1) {
+                line.push(char::from(b'0' + rng.range(1..10) as u8));
+            } else if rng.chance(words) {
+                let word = *rng.pick(&NUMBER_WORDS);
+                line.push_str(word);
+            } else {
+                line.push(char::from(b'a' + rng.range(0..26) as u8));
+            }
+        }
+        writeln!(out, "{line}").unwrap();
+    }
+    out
+}
+
+fn day2(rng: &mut Rng, games: usize, sets: usize, cubes: u64) -> String {
+    let mut out = String::new();
+    for id in 1..=games {
+        let sets: Vec<String> = (0..rng.range(1..sets.max(1) as u64 + 1))
+            .map(|_| {
+                let mut colors = vec!["red", "green", "blue"];
+                rng.shuffle(&mut colors);
+                let count = rng.range(1..4) as usize;
+                colors[..count]
+                    .iter()
+                    .map(|c| format!("{} {c}", rng.range(1..cubes.max(1) + 1)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .collect();
+        writeln!(out, "Game {id}: {}", sets.join("; ")).unwrap();
+    }
+    out
+}
+
+const SYMBOLS: [char; 10] = ['*', '#', '+', '$', '/', '@', '=', '%', '&', '-'];
+
+// `density` is the share of cells holding a symbol. Numbers are always
+// separated by at least one other cell on their line.
+fn day3(rng: &mut Rng, width: usize, height: usize, density: f64) -> String {
+    let mut out = String::new();
+    for _ in 0..height {
+        let mut line = String::new();
+        while line.len() < width {
+            let room = width - line.len();
+            let after_number = line.ends_with(|c: char| c.is_ascii_digit());
+            if !after_number && room > 1 && rng.chance(0.15) {
+                let len = rng.range(1..4.min(room as u64)) as u32;
+                let n = rng.range(10_u64.pow(len - 1)..10_u64.pow(len));
+                line.push_str(&n.to_string());
+            } else if rng.chance(density) {
+                line.push(*rng.pick(&SYMBOLS));
+            } else {
+                line.push('.');
+            }
+        }
+        writeln!(out, "{line}").unwrap();
+    }
+    out
+}
+
+// Each of the card's numbers is a winning one with a probability of `match_rate`.
+// Part 2 grows exponentially with it, so a card gets fewer matches when its
+// score or the copies it wins would overflow a u64.
+fn day4(rng: &mut Rng, cards: usize, winning: usize, numbers: usize, match_rate: f64) -> String {
+    let mut out = String::new();
+    // Indexed by id, copies[0] is unused
+    let mut copies = vec![1_u64; cards + 1];
+    let (mut total_score, mut total_copies) = (0_u64, cards as u64);
+    for id in 1..=cards {
+        let fits = |matches: usize| {
+            let score = match matches {
+                0 => Some(0),
+                m => 1_u64.checked_shl(m as u32 - 1),
+            };
+            let won = matches.min(cards - id) as u64;
+            score.and_then(|s| total_score.checked_add(s)).is_some()
+                && won
+                    .checked_mul(copies[id])
+                    .and_then(|c| total_copies.checked_add(c))
+                    .is_some()
+        };
+        let max_matches = (0..=numbers).take_while(|&m| fits(m)).last().unwrap();
+
+        let mut pool: Vec<u64> = (1..100).collect();
+        rng.shuffle(&mut pool);
+        let (winning_numbers, others) = pool.split_at(winning.min(99));
+        let (mut w, mut o) = (winning_numbers.iter(), others.iter());
+        let mut card_numbers: Vec<u64> = vec![];
+        let mut matches = 0;
+        for _ in 0..numbers {
+            let can_match = matches < max_matches;
+            let n = match rng.chance(match_rate) && can_match {
+                true => w.next().or_else(|| o.next()),
+                false => o.next().or_else(|| w.next().filter(|_| can_match)),
+            };
+            if let Some(&n) = n {
+                matches += winning_numbers.contains(&n) as usize;
+                card_numbers.push(n);
+            }
+        }
+
+        total_score += match matches {
+            0 => 0,
+            m => 1 << (m - 1),
+        };
+        for won in id + 1..=(id + matches).min(cards) {
+            copies[won] += copies[id];
+            total_copies += copies[id];
+        }
+
+        let format = |ns: &[u64]| {
+            ns.iter()
+                .map(|n| format!("{n:>2}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        writeln!(
+            out,
+            "Card {id:>3}: {} | {}",
+            format(winning_numbers),
+            format(&card_numbers)
+        )
+        .unwrap();
+    }
+    out
+}
+
+const CATEGORIES: [&str; 8] = [
+    "seed",
+    "soil",
+    "fertilizer",
+    "water",
+    "light",
+    "temperature",
+    "humidity",
+    "location",
+];
+
+// Every map cuts [0, max) in blocks and moves them around, like the real input.
+// Half of the blocks are left out, and behave as identity.
+fn day5(
+    rng: &mut Rng,
+    maps: usize,
+    ranges: usize,
+    seeds: usize,
+    seed_length: u64,
+    max: u64,
+) -> String {
+    let categories: Vec<String> = match maps {
+        7 => CATEGORIES.iter().map(|c| c.to_string()).collect(),
+        _ => std::iter::once("seed".to_string())
+            .chain((1..maps).map(|i| format!("stage{i}")))
+            .chain(std::iter::once("location".to_string()))
+            .collect(),
+    };
+
+    let mut out = String::from("seeds:");
+    for _ in 0..seeds {
+        let length = rng.range(1..seed_length.max(1) + 1);
+        let start = rng.range(0..max.saturating_sub(length).max(1));
+        write!(out, " {start} {length}").unwrap();
+    }
+    out.push('\n');
+
+    for w in categories.windows(2) {
+        let blocks = (ranges * 2).max(1);
+        let mut cuts: Vec<u64> = (1..blocks).map(|_| rng.range(1..max.max(2))).collect();
+        cuts.push(0);
+        cuts.push(max);
+        cuts.sort_unstable();
+        cuts.dedup();
+        let sources: Vec<(u64, u64)> = cuts.windows(2).map(|c| (c[0], c[1] - c[0])).collect();
+
+        let mut order: Vec<usize> = (0..sources.len()).collect();
+        rng.shuffle(&mut order);
+        let mut destination = 0;
+        let mut fns = vec![];
+        for i in order {
+            let (from, size) = sources[i];
+            fns.push((destination, from, size));
+            destination += size;
+        }
+        rng.shuffle(&mut fns);
+
+        write!(out, "\n{}-to-{} map:\n", w[0], w[1]).unwrap();
+        for (to, from, size) in fns.iter().take(ranges) {
+            writeln!(out, "{to} {from} {size}").unwrap();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generate::{generate, Options};
+    use crate::puzzle::AocPuzzle;
+    use crate::{day4, day5, solve};
+
+    #[test]
+    fn test_generated_inputs_are_solvable() {
+        for day in ["day1", "day2", "day3", "day4", "day5"] {
+            let input = generate(day, &Options::from_strs(&["--seed", "7"]));
+            assert_eq!(input, generate(day, &Options::from_strs(&["--seed", "7"])));
+            assert_ne!(input, generate(day, &Options::from_strs(&["--seed", "8"])));
+            solve(day, AocPuzzle::PartOne, &input).unwrap();
+            solve(day, AocPuzzle::PartTwo, &input).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_size_knobs() {
+        let input = generate(
+            "day3",
+            &Options::from_strs(&["--width", "12", "--height", "5"]),
+        );
+        assert_eq!(input.lines().count(), 5);
+        assert!(input.lines().all(|l| l.len() == 12));
+
+        let input = generate(
+            "day4",
+            &Options::from_strs(&["--cards", "50", "--match-rate", "0"]),
+        );
+        assert_eq!(input.lines().count(), 50);
+        assert_eq!(day4::solve(&input, AocPuzzle::PartOne), Ok(0));
+        assert_eq!(day4::solve(&input, AocPuzzle::PartTwo), Ok(50));
+
+        let input = generate(
+            "day5",
+            &Options::from_strs(&["--maps", "3", "--ranges", "4"]),
+        );
+        assert!(input.contains("seed-to-stage1 map:"));
+        assert!(input.contains("stage2-to-location map:"));
+        assert_eq!(input.lines().count(), 2 + 3 * 6 - 1);
+        assert_eq!(day5::lint(&input), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_day4_stays_solvable() {
+        let settings: [&[&str]; 3] = [
+            &["--match-rate", "0.3"],
+            &[
+                "--winning",
+                "60",
+                "--numbers",
+                "80",
+                "--match-rate",
+                "1",
+                "--cards",
+                "3",
+            ],
+            // Every number a winning one, on many cards
+            &[
+                "--winning",
+                "99",
+                "--numbers",
+                "99",
+                "--match-rate",
+                "1",
+                "--cards",
+                "1000",
+            ],
+        ];
+        for args in settings {
+            let input = generate("day4", &Options::from_strs(args));
+            assert!(day4::solve(&input, AocPuzzle::PartOne).is_ok(), "{args:?}");
+            assert!(day4::solve(&input, AocPuzzle::PartTwo).is_ok(), "{args:?}");
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use puzzle::{AocPuzzle, SolveError};
+
+#[cfg(test)]
+mod bench;
+pub mod day1;
+pub mod day2;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod examples;
+pub mod fuzz;
+pub mod generate;
+pub mod parse;
+pub mod puzzle;
+mod rng;
+pub mod runner;
+pub mod scaffold;
+pub mod watch;
+
+pub type Solver = fn(&str, AocPuzzle) -> Result<u64, SolveError>;
+
+pub fn solver(day: &str) -> Option<Solver> {
+    match day {
+        "day1" => Some(|input, puzzle| Ok(day1::solve(input, puzzle)?)),
+        "day2" => Some(|input, puzzle| Ok(day2::solve(input, puzzle)?)),
+        "day3" => Some(|input, puzzle| Ok(day3::solve(input, puzzle)?.into())),
+        "day4" => Some(|input, puzzle| Ok(day4::solve(input, puzzle)?)),
+        "day5" => Some(|input, puzzle| Ok(day5::solve(input, puzzle)?)),
+        _ => None,
+    }
+}
+
+// The days known to `solver`, in order
+pub fn days() -> Vec<String> {
+    (1..=25)
+        .map(|n| format!("day{n}"))
+        .filter(|day| solver(day).is_some())
+        .collect()
+}
+
+pub fn solve(day: &str, puzzle: AocPuzzle, input: &str) -> Result<u64, SolveError> {
+    let solver = solver(day).unwrap_or_else(|| panic!("{day} does not exist yet"));
+    solver(input, puzzle)
+}
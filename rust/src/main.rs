@@ -1,34 +1,217 @@
 use std::env;
 
-use puzzle::AocPuzzle;
-mod day1;
-mod day2;
-mod day3;
-mod day4;
-mod day5;
-
-mod puzzle;
-
-fn run_day(day: &str, puzzle: puzzle::AocPuzzle) -> u32 {
-    match day {
-        "day1" => day1::day_1(puzzle),
-        "day2" => day2::day_2(puzzle),
-        "day3" => day3::day_3(puzzle),
-        "day4" => day4::day_4(puzzle),
-        "day5" => day5::day_5(puzzle).try_into().unwrap(),
-        _ => panic!("Day not implemented yet."),
-    }
+use aoc::parse::ParseError;
+use aoc::puzzle::AocPuzzle;
+use aoc::runner::{self, Format, Status};
+use aoc::{day1, day2, day4, day5, generate, puzzle, scaffold, watch};
+
+// Reports an input that couldn't be read instead of going on with it
+fn or_exit<T>(result: Result<T, ParseError>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1)
+    })
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let day = &args[1];
-    let aoc_puzzle = match args[2].as_str() {
-        "part1" => AocPuzzle::PartOne,
-        "part2" => AocPuzzle::PartTwo,
-        _ => panic!(""),
+    let mut args: Vec<String> = env::args().collect();
+    // e.g. `day3 part1 --format json`
+    let format = match args.iter().position(|a| a == "--format") {
+        Some(i) => {
+            let name: Vec<String> = args.drain(i..(i + 2).min(args.len())).collect();
+            name.get(1)
+                .and_then(|n| Format::from_name(n))
+                .expect("Expected --format text or --format json")
+        }
+        None => Format::Text,
     };
+    let day = &args[1];
+    // e.g. `generate day3 --width 50 --height 50 --seed 1`
+    if day == "generate" {
+        let options = generate::Options::from_args(&args[3..]);
+        print!("{}", generate::generate(&args[2], &options));
+        return;
+    }
+
+    // e.g. `new day6`
+    if day == "new" {
+        match scaffold::new_day(&puzzle::root(), &args[2]) {
+            Ok(created) => {
+                for path in created {
+                    println!("created {}", path.display());
+                }
+                println!("registered {} in src/lib.rs", args[2]);
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // e.g. `watch day3`, until interrupted
+    if day == "watch" {
+        watch::watch(&puzzle::root(), &args[2]);
+        return;
+    }
+
+    if day == "lint" {
+        let issues = match args[2].as_str() {
+            "day5" => day5::lint(&puzzle::get_input("day5")),
+            other => panic!("No lints for {other}"),
+        };
+        for issue in &issues {
+            println!("{issue}");
+        }
+        if !issues.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // e.g. `day1 part1 --digits unicode`
+    if day == "day1" && args.get(3).is_some_and(|a| a == "--digits") {
+        let aoc_puzzle = AocPuzzle::from_name(&args[2]).expect("Expected part1 or part2");
+        let digits = args
+            .get(4)
+            .and_then(|name| day1::Digits::from_name(name))
+            .expect("Expected --digits ascii or --digits unicode");
+        println!(
+            "{}",
+            or_exit(day1::solve_with(
+                &puzzle::get_input(day),
+                aoc_puzzle,
+                digits
+            ))
+        );
+        return;
+    }
+
+    // e.g. `day2 bags 39`, for a bag of at most 39 cubes
+    if day == "day2" && args[2] == "bags" {
+        let budget = args[3].parse().expect("Expected a number of cubes");
+        let games = or_exit(day2::parse_games(&puzzle::get_input(day)));
+        for plan in day2::best_bags(&games, budget) {
+            let games: Vec<String> = plan.games.iter().map(|id| id.to_string()).collect();
+            println!(
+                "{} ({} cubes): {} games ({})",
+                plan.bag,
+                plan.bag.total(),
+                games.len(),
+                games.join(", ")
+            );
+        }
+        return;
+    }
+
+    // e.g. `day2 cheapest 1 2 5`
+    if day == "day2" && args[2] == "cheapest" {
+        let ids: Vec<u32> = args[3..]
+            .iter()
+            .map(|id| id.parse().expect("Expected game ids"))
+            .collect();
+        let games = or_exit(day2::parse_games(&puzzle::get_input(day)));
+        match day2::cheapest_bag(&games, &ids) {
+            Ok(bag) => println!("{bag} ({} cubes)", bag.total()),
+            Err(id) => {
+                eprintln!("No game {id}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    let res = run_day(day, aoc_puzzle);
-    println!("{res}");
+    if day == "day4" && args[2] == "report" {
+        print!("{}", or_exit(day4::report(&puzzle::get_input(day))));
+        return;
+    }
+
+    if day == "day5" && args[2] == "--trace" {
+        let seed = args[3].parse().expect("Expected a number");
+        for step in or_exit(day5::trace(&puzzle::get_input(day), seed))
+            .expect("No maps from seed to location")
+        {
+            println!("{step}");
+        }
+        return;
+    }
+
+    if day == "day5" && args[2] == "--brute-force" {
+        let threads = match args.get(3) {
+            Some(n) => n.parse().expect("Expected a number of threads"),
+            None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+        if !or_exit(day5::brute_force(&puzzle::get_input(day), threads)) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if day == "day5" && args[2] == "compose" {
+        match or_exit(day5::compose(&puzzle::get_input(day), &args[3], &args[4])) {
+            Some(map) => print!("{map}"),
+            None => println!("No maps from {} to {}", args[3], args[4]),
+        }
+        return;
+    }
+
+    // e.g. `day5 location 0..100 seed`
+    if day == "day5" && args.len() == 5 && args[3].contains("..") {
+        let (start, end) = args[3].split_once("..").unwrap();
+        let range =
+            start.parse().expect("Expected a number")..end.parse().expect("Expected a number");
+        match or_exit(day5::convert_back(
+            &puzzle::get_input(day),
+            &args[2],
+            range,
+            &args[4],
+        )) {
+            Some(res) => println!("{res:?}"),
+            None => println!("No maps from {} to {}", args[4], args[2]),
+        }
+        return;
+    }
+
+    // e.g. `day5 seed 79 humidity`, or `day5 location 82 seed` for every seed that gets there
+    if day == "day5" && args.len() == 5 {
+        let n = args[3].parse().expect("Expected a number");
+        let input = puzzle::get_input(day);
+        if let Some(res) = or_exit(day5::convert(&input, &args[2], n, &args[4])) {
+            println!("{res}");
+        } else if let Some(res) = or_exit(day5::preimage(&input, &args[2], n, &args[4])) {
+            println!("{res:?}");
+        } else {
+            println!("No maps from {} to {}", args[2], args[4]);
+        }
+        return;
+    }
+
+    // e.g. `all --format json`, one line per part
+    if day == "all" {
+        let mut failed = false;
+        for run in runner::run_all() {
+            match format {
+                Format::Json => println!("{}", run.to_json()),
+                Format::Text => println!("{}", run.summary()),
+            }
+            failed |= matches!(run.status, Status::Error(_));
+        }
+        if failed {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let aoc_puzzle = AocPuzzle::from_name(&args[2]).expect("Expected part1 or part2");
+
+    let run = runner::run(day, aoc_puzzle);
+    match (format, &run.status) {
+        (Format::Json, _) => println!("{}", run.to_json()),
+        (Format::Text, Status::Ok(_)) => println!("{run}"),
+        (Format::Text, _) => eprintln!("{run}"),
+    }
+    if let Status::Error(_) = run.status {
+        std::process::exit(1);
+    }
 }
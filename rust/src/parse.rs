@@ -0,0 +1,274 @@
+use std::fmt;
+use std::str::FromStr;
+
+// 1-based, columns count chars
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ErrorKind {
+    Expected(String),
+    Invalid(&'static str, String),
+    Duplicate(&'static str, String),
+    Overflow(&'static str),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub position: Position,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: ",
+            self.position.line, self.position.column
+        )?;
+        match &self.kind {
+            ErrorKind::Expected(what) => write!(f, "expected {what}"),
+            ErrorKind::Invalid(what, found) => write!(f, "invalid {what} `{found}`"),
+            ErrorKind::Duplicate(what, found) => write!(f, "duplicate {what} `{found}`"),
+            ErrorKind::Overflow(what) => write!(f, "{what} overflows a u64"),
+        }
+    }
+}
+
+// A slice of the input that knows where it starts, so that errors can point at it
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Text<'a> {
+    text: &'a str,
+    position: Position,
+}
+
+// A `label:` line and the lines under it
+#[derive(Debug, PartialEq)]
+pub struct Block<'a> {
+    pub label: Text<'a>,
+    pub lines: Vec<Text<'a>>,
+}
+
+impl<'a> Text<'a> {
+    pub fn new(text: &'a str) -> Text<'a> {
+        Text {
+            text,
+            position: Position { line: 1, column: 1 },
+        }
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    // `part` has to be a slice of this text
+    pub fn sub(&self, part: &'a str) -> Text<'a> {
+        let offset = (part.as_ptr() as usize)
+            .checked_sub(self.text.as_ptr() as usize)
+            .filter(|offset| offset + part.len() <= self.text.len())
+            .expect("Not a slice of this text");
+        let before = &self.text[..offset];
+        let position = match before.rfind('\n') {
+            Some(i) => Position {
+                line: self.position.line + before.matches('\n').count(),
+                column: before[i + 1..].chars().count() + 1,
+            },
+            None => Position {
+                line: self.position.line,
+                column: self.position.column + before.chars().count(),
+            },
+        };
+        Text {
+            text: part,
+            position,
+        }
+    }
+
+    pub fn error(&self, kind: ErrorKind) -> ParseError {
+        ParseError {
+            position: self.position,
+            kind,
+        }
+    }
+
+    pub fn expected(&self, what: &str) -> ParseError {
+        self.error(ErrorKind::Expected(what.to_string()))
+    }
+
+    pub fn trim(self) -> Text<'a> {
+        self.sub(self.text.trim())
+    }
+
+    // Counted as we go, `sub` would rescan the text for every line
+    pub fn lines(self) -> impl Iterator<Item = Text<'a>> {
+        self.text.lines().enumerate().map(move |(i, line)| Text {
+            text: line,
+            position: match i {
+                0 => self.position,
+                _ => Position {
+                    line: self.position.line + i,
+                    column: 1,
+                },
+            },
+        })
+    }
+
+    pub fn words(self) -> impl Iterator<Item = Text<'a>> {
+        self.text.split_whitespace().map(move |word| self.sub(word))
+    }
+
+    pub fn split(self, separator: &'a str) -> impl Iterator<Item = Text<'a>> {
+        self.text.split(separator).map(move |part| self.sub(part))
+    }
+
+    pub fn split_once(self, separator: &str) -> Result<(Text<'a>, Text<'a>), ParseError> {
+        let (left, right) = self
+            .text
+            .split_once(separator)
+            .ok_or_else(|| self.expected(&format!("`{separator}`")))?;
+        Ok((self.sub(left), self.sub(right)))
+    }
+
+    pub fn strip_prefix(self, prefix: &str) -> Result<Text<'a>, ParseError> {
+        match self.text.strip_prefix(prefix) {
+            Some(rest) => Ok(self.sub(rest)),
+            None => Err(self.expected(&format!("`{prefix}`"))),
+        }
+    }
+
+    pub fn strip_suffix(self, suffix: &str) -> Result<Text<'a>, ParseError> {
+        match self.text.strip_suffix(suffix) {
+            Some(rest) => Ok(self.sub(rest)),
+            None => Err(self.expected(&format!("`{suffix}`"))),
+        }
+    }
+
+    pub fn parse<T: FromStr>(self, what: &'static str) -> Result<T, ParseError> {
+        self.text
+            .parse()
+            .map_err(|_| self.error(ErrorKind::Invalid(what, self.text.to_string())))
+    }
+
+    // Whitespace separated, e.g. `79 14 55 13`
+    pub fn numbers<T: FromStr>(self) -> Result<Vec<T>, ParseError> {
+        self.words().map(|word| word.parse("number")).collect()
+    }
+
+    // `key: value`, both trimmed
+    pub fn header(self) -> Result<(Text<'a>, Text<'a>), ParseError> {
+        let (key, value) = self.split_once(":")?;
+        Ok((key.trim(), value.trim()))
+    }
+
+    // Non-blank lines, grouped under the last line ending with `:` before them.
+    // Lines that come before any label are returned apart.
+    pub fn blocks(self) -> (Vec<Text<'a>>, Vec<Block<'a>>) {
+        let mut loose = vec![];
+        let mut blocks: Vec<Block> = vec![];
+        for line in self.lines().map(Text::trim).filter(|l| !l.is_empty()) {
+            match (line.text.strip_suffix(':'), blocks.last_mut()) {
+                (Some(label), _) => blocks.push(Block {
+                    label: line.sub(label),
+                    lines: vec![],
+                }),
+                (None, Some(block)) => block.lines.push(line),
+                (None, None) => loose.push(line),
+            }
+        }
+        (loose, blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorKind, ParseError, Position, Text};
+
+    fn at(text: Text<'_>) -> (&str, usize, usize) {
+        (text.as_str(), text.position().line, text.position().column)
+    }
+
+    #[test]
+    fn test_positions() {
+        let text = Text::new("ab\n  cé d\n\nxyz");
+        let lines: Vec<_> = text.lines().map(at).collect();
+        assert_eq!(
+            lines,
+            vec![("ab", 1, 1), ("  cé d", 2, 1), ("", 3, 1), ("xyz", 4, 1)]
+        );
+        let words: Vec<_> = text.words().map(at).collect();
+        assert_eq!(
+            words,
+            vec![("ab", 1, 1), ("cé", 2, 3), ("d", 2, 6), ("xyz", 4, 1)]
+        );
+        let (left, right) = text.lines().nth(1).unwrap().split_once("é").unwrap();
+        assert_eq!((at(left), at(right)), (("  c", 2, 1), (" d", 2, 5)));
+        assert_eq!(at(right.trim()), ("d", 2, 6));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not a slice of this text")]
+    fn test_sub_outside() {
+        let other = String::from("abc");
+        Text::new("abc").sub(&other);
+    }
+
+    #[test]
+    fn test_helpers() {
+        let line = Text::new("Card 12: 41 48 | 83 86");
+        let (key, value) = line.header().unwrap();
+        assert_eq!(at(key), ("Card 12", 1, 1));
+        assert_eq!(at(value), ("41 48 | 83 86", 1, 10));
+        assert_eq!(key.strip_prefix("Card").unwrap().trim().parse("id"), Ok(12));
+        assert_eq!(
+            value.split("|").map(|p| p.numbers()).collect::<Vec<_>>(),
+            vec![Ok(vec![41, 48]), Ok(vec![83, 86])]
+        );
+        assert_eq!(
+            value.numbers::<u8>(),
+            Err(ParseError {
+                position: Position {
+                    line: 1,
+                    column: 16
+                },
+                kind: ErrorKind::Invalid("number", "|".to_string())
+            })
+        );
+        assert_eq!(
+            key.strip_suffix("map").map_err(|e| e.to_string()),
+            Err("line 1, column 1: expected `map`".to_string())
+        );
+        assert_eq!(
+            Text::new("seeds 1 2").header().map_err(|e| e.to_string()),
+            Err("line 1, column 1: expected `:`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let (loose, blocks) =
+            Text::new("seeds: 1 2\n\na map:\n1 2 3\n\n 4 5 6 \nb map:\n").blocks();
+        assert_eq!(
+            loose.into_iter().map(at).collect::<Vec<_>>(),
+            vec![("seeds: 1 2", 1, 1)]
+        );
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(at(blocks[0].label), ("a map", 3, 1));
+        assert_eq!(
+            blocks[0].lines.iter().copied().map(at).collect::<Vec<_>>(),
+            vec![("1 2 3", 4, 1), ("4 5 6", 6, 2)]
+        );
+        assert_eq!(at(blocks[1].label), ("b map", 7, 1));
+        assert!(blocks[1].lines.is_empty());
+    }
+}
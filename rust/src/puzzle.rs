@@ -1,4 +1,181 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::parse::ParseError;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum AocPuzzle {
     PartOne,
     PartTwo,
 }
+
+impl AocPuzzle {
+    pub fn from_name(name: &str) -> Option<AocPuzzle> {
+        match name {
+            "part1" => Some(AocPuzzle::PartOne),
+            "part2" => Some(AocPuzzle::PartTwo),
+            _ => None,
+        }
+    }
+}
+
+// Returned by a day's `solve` for the parts it doesn't solve yet, or for
+// inputs it can't make sense of
+#[derive(Debug, PartialEq)]
+pub enum SolveError {
+    Unimplemented,
+    Invalid(ParseError),
+}
+
+impl From<ParseError> for SolveError {
+    fn from(e: ParseError) -> SolveError {
+        SolveError::Invalid(e)
+    }
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::Unimplemented => write!(f, "not implemented"),
+            SolveError::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+// Inputs, examples and day modules are all found from the directory `aoc`
+// runs in
+pub fn root() -> PathBuf {
+    env::current_dir().expect("Should have been able to read the current directory")
+}
+
+// `day4` reads `puzzle_4_1`
+pub fn input_path(root: &Path, day: &str) -> PathBuf {
+    let n = day.trim_start_matches("day");
+    root.join(format!("puzzle_{n}_1"))
+}
+
+pub fn get_input(day: &str) -> String {
+    read_input(&input_path(&root(), day)).expect("Should have been able to read the file")
+}
+
+// Every input file goes through here, so solvers can take their text as-is
+pub fn read_input(path: &Path) -> io::Result<String> {
+    Ok(normalized(fs::read_to_string(path)?))
+}
+
+// Keeps the text as it is when there is nothing to normalize, which is the
+// usual case, so that big inputs aren't copied
+pub fn normalized(input: String) -> String {
+    if is_normalized(&input) {
+        input
+    } else {
+        normalize(&input)
+    }
+}
+
+fn is_normalized(input: &str) -> bool {
+    let Some(body) = input.strip_suffix('\n') else {
+        return input.is_empty();
+    };
+    !body.is_empty()
+        && !body.starts_with(['\u{feff}', '\n'])
+        && !body.ends_with('\n')
+        && body.split('\n').all(|l| l.len() == l.trim_end().len())
+}
+
+// Drops a UTF-8 BOM, `\r\n` line endings, trailing whitespace and blank lines
+// around the input, so that every day sees the same text whatever the editor.
+// Blank lines in between are kept: day5 uses them to separate its maps.
+pub fn normalize(input: &str) -> String {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let lines: Vec<&str> = input.lines().map(str::trim_end).collect();
+    let start = lines
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(start, |i| i + 1);
+    let mut normalized = lines[start..end].join("\n");
+    if !normalized.is_empty() {
+        normalized.push('\n');
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{input_path, is_normalized, normalize, AocPuzzle};
+    use crate::{days, solve};
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("a\nb\n"), "a\nb\n");
+        assert_eq!(normalize("a\nb"), "a\nb\n");
+        assert_eq!(normalize("\u{feff}a\r\nb\r\n"), "a\nb\n");
+        assert_eq!(normalize("a \t\n  b  \n"), "a\n  b\n");
+        assert_eq!(normalize("\n\r\n a\n\n\nb\n\n  \n"), " a\n\n\nb\n");
+        assert_eq!(normalize(""), "");
+        assert_eq!(normalize("\u{feff}\r\n \n"), "");
+        // Only at the start
+        assert_eq!(normalize("a\u{feff}"), "a\u{feff}\n");
+    }
+
+    #[test]
+    fn test_input_path() {
+        let root = Path::new("/aoc");
+        assert_eq!(input_path(root, "day4"), root.join("puzzle_4_1"));
+    }
+
+    #[test]
+    fn test_is_normalized() {
+        for input in [
+            "a\nb\n",
+            "a\nb",
+            "\u{feff}a\r\nb\r\n",
+            "a\r\n",
+            "a\rb\n",
+            "a \t\n  b  \n",
+            "\n\r\n a\n\n\nb\n\n  \n",
+            " a\n\n\nb\n",
+            "a\n\n",
+            "\n",
+            "",
+            "\u{feff}\r\n \n",
+            "a\u{feff}\n",
+        ] {
+            assert_eq!(is_normalized(input), normalize(input) == input, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_line_endings_and_bom() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        for day in days() {
+            let Ok(input) = fs::read_to_string(input_path(root, &day)) else {
+                continue;
+            };
+            let crlf = input.replace('\n', "\r\n");
+            let variants = [
+                format!("\u{feff}{input}"),
+                crlf.clone(),
+                format!("\u{feff}{crlf}"),
+                format!("\n{}\n\n", input.replace('\n', "  \n")),
+            ];
+            for puzzle in [AocPuzzle::PartOne, AocPuzzle::PartTwo] {
+                let expected = solve(&day, puzzle, &normalize(&input));
+                for variant in &variants {
+                    let actual = solve(&day, puzzle, &normalize(variant));
+                    assert_eq!(actual, expected, "{day} {puzzle:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, UnwindSafe};
+use std::time::{Duration, Instant};
+
+use crate::puzzle::{self, AocPuzzle, SolveError};
+use crate::{days, solve};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Status {
+    Ok(u64),
+    Error(String),
+    Unimplemented,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub day: String,
+    pub puzzle: AocPuzzle,
+    pub status: Status,
+    pub elapsed: Duration,
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(s) => s.to_string(),
+        None => payload
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| "panicked".to_string()),
+    }
+}
+
+// `unimplemented!()` and `todo!()` panic with these messages
+fn is_unimplemented(message: &str) -> bool {
+    message.starts_with("not implemented") || message.starts_with("not yet implemented")
+}
+
+fn run_with(
+    day: &str,
+    puzzle: AocPuzzle,
+    f: impl FnOnce() -> (Result<u64, SolveError>, Duration) + UnwindSafe,
+) -> Run {
+    // Only bugs end up unwinding here: bad inputs come back as `SolveError::Invalid`
+    let (status, elapsed) = match panic::catch_unwind(f) {
+        Ok((Ok(answer), elapsed)) => (Status::Ok(answer), elapsed),
+        Ok((Err(SolveError::Unimplemented), _)) => (Status::Unimplemented, Duration::ZERO),
+        Ok((Err(SolveError::Invalid(e)), _)) => (Status::Error(e.to_string()), Duration::ZERO),
+        Err(payload) => {
+            let message = panic_message(&*payload);
+            match is_unimplemented(&message) {
+                true => (Status::Unimplemented, Duration::ZERO),
+                false => (Status::Error(message), Duration::ZERO),
+            }
+        }
+    };
+    Run {
+        day: day.to_string(),
+        puzzle,
+        status,
+        elapsed,
+    }
+}
+
+// Reads the input and solves one part. Invalid inputs and panics are reported in the status.
+pub fn run(day: &str, puzzle: AocPuzzle) -> Run {
+    run_with(day, puzzle, || {
+        let input = puzzle::get_input(day);
+        let start = Instant::now();
+        let answer = solve(day, puzzle, &input);
+        (answer, start.elapsed())
+    })
+}
+
+// Same as `run`, on a given input
+pub fn run_on(day: &str, puzzle: AocPuzzle, input: &str) -> Run {
+    run_with(day, puzzle, || {
+        let start = Instant::now();
+        let answer = solve(day, puzzle, input);
+        (answer, start.elapsed())
+    })
+}
+
+// Both parts of every day. A failing part doesn't stop the others.
+pub fn run_all() -> impl Iterator<Item = Run> {
+    days().into_iter().flat_map(|day| {
+        [AocPuzzle::PartOne, AocPuzzle::PartTwo]
+            .into_iter()
+            .map(move |puzzle| run(&day, puzzle))
+    })
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl Run {
+    fn part(&self) -> u8 {
+        match self.puzzle {
+            AocPuzzle::PartOne => 1,
+            AocPuzzle::PartTwo => 2,
+        }
+    }
+
+    // The day and part along with the result, for runs of several parts
+    pub fn summary(&self) -> String {
+        match self.status {
+            Status::Ok(answer) => format!(
+                "{} part {}: {answer} ({:.3} ms)",
+                self.day,
+                self.part(),
+                self.elapsed.as_secs_f64() * 1000.0
+            ),
+            _ => self.to_string(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let (status, answer, error) = match &self.status {
+            Status::Ok(answer) => ("ok", answer.to_string(), "null".to_string()),
+            Status::Error(message) => ("error", "null".to_string(), json_string(message)),
+            Status::Unimplemented => ("unimplemented", "null".to_string(), "null".to_string()),
+        };
+        format!(
+            "{{\"day\":{},\"part\":{},\"status\":\"{status}\",\"answer\":{answer},\"time_ms\":{:.3},\"error\":{error}}}",
+            json_string(&self.day),
+            self.part(),
+            self.elapsed.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+// A bare answer, so that scripts reading it keep working
+impl fmt::Display for Run {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.status {
+            Status::Ok(answer) => write!(f, "{answer}"),
+            Status::Error(message) => {
+                write!(f, "{} part {} failed: {message}", self.day, self.part())
+            }
+            Status::Unimplemented => {
+                write!(f, "{} part {} is not implemented", self.day, self.part())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::parse::Text;
+    use crate::puzzle::{AocPuzzle, SolveError};
+    use crate::solve;
+
+    use super::{json_string, run_on, run_with, Run, Status};
+
+    #[test]
+    fn test_statuses() {
+        let run = run_with("day1", AocPuzzle::PartOne, || {
+            (Ok(42), Duration::from_millis(3))
+        });
+        assert_eq!(run.status, Status::Ok(42));
+        assert_eq!(run.elapsed, Duration::from_millis(3));
+
+        let status = |f: fn() -> (Result<u64, SolveError>, Duration)| {
+            run_with("day1", AocPuzzle::PartTwo, f).status
+        };
+        assert_eq!(
+            status(|| (Err(SolveError::Unimplemented), Duration::from_millis(3))),
+            Status::Unimplemented
+        );
+        assert_eq!(
+            status(|| (
+                Err(Text::new("abc").expected("a digit").into()),
+                Duration::from_millis(3)
+            )),
+            Status::Error("line 1, column 1: expected a digit".to_string())
+        );
+        assert_eq!(status(|| unimplemented!()), Status::Unimplemented);
+        assert_eq!(status(|| todo!("later")), Status::Unimplemented);
+        assert_eq!(status(|| panic!("boom")), Status::Error("boom".to_string()));
+        assert_eq!(
+            status(|| panic!("line {}: bad", 3)),
+            Status::Error("line 3: bad".to_string())
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let run = |status| Run {
+            day: "day5".to_string(),
+            puzzle: AocPuzzle::PartTwo,
+            status,
+            elapsed: Duration::from_micros(1_500),
+        };
+        assert_eq!(
+            run(Status::Ok(46)).to_json(),
+            r#"{"day":"day5","part":2,"status":"ok","answer":46,"time_ms":1.500,"error":null}"#
+        );
+        assert_eq!(
+            run(Status::Unimplemented).to_json(),
+            r#"{"day":"day5","part":2,"status":"unimplemented","answer":null,"time_ms":1.500,"error":null}"#
+        );
+        assert_eq!(
+            run(Status::Error("no \"seeds:\" line".to_string())).to_json(),
+            r#"{"day":"day5","part":2,"status":"error","answer":null,"time_ms":1.500,"error":"no \"seeds:\" line"}"#
+        );
+        assert_eq!(json_string("a\\b\n\u{1}"), r#""a\\b\n\u0001""#);
+    }
+
+    #[test]
+    fn test_text() {
+        let run = |status| Run {
+            day: "day5".to_string(),
+            puzzle: AocPuzzle::PartTwo,
+            status,
+            elapsed: Duration::ZERO,
+        };
+        assert_eq!(run(Status::Ok(46)).to_string(), "46");
+        assert_eq!(run(Status::Ok(46)).summary(), "day5 part 2: 46 (0.000 ms)");
+        assert_eq!(
+            run(Status::Unimplemented).summary(),
+            "day5 part 2 is not implemented"
+        );
+        assert_eq!(
+            run(Status::Unimplemented).to_string(),
+            "day5 part 2 is not implemented"
+        );
+        assert_eq!(
+            run(Status::Error("boom".to_string())).to_string(),
+            "day5 part 2 failed: boom"
+        );
+    }
+
+    // Bad inputs come back as errors, a panic would fail the test
+    #[test]
+    fn test_invalid_inputs() {
+        for (day, input, message) in [
+            (
+                "day2",
+                "Game 1: 3 purple",
+                "line 1, column 11: invalid color `purple`",
+            ),
+            (
+                "day3",
+                "..*\n.99999999999.",
+                "line 2, column 2: invalid number `99999999999`",
+            ),
+            ("day4", "Card 1 41 | 83", "line 1, column 1: expected `:`"),
+            (
+                "day5",
+                "seeds: 1 x",
+                "line 1, column 10: invalid number `x`",
+            ),
+        ] {
+            let error = solve(day, AocPuzzle::PartOne, input).unwrap_err();
+            assert!(matches!(error, SolveError::Invalid(_)), "{day}");
+            assert_eq!(error.to_string(), message);
+            assert_eq!(
+                run_on(day, AocPuzzle::PartOne, input).status,
+                Status::Error(message.to_string())
+            );
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::puzzle;
+
+const TEMPLATE: &str = r#"use crate::puzzle::{AocPuzzle, SolveError};
+
+pub fn solve(input: &str, puzzle: AocPuzzle) -> Result<u64, SolveError> {
+    match puzzle {
+        AocPuzzle::PartOne => part_1(input),
+        AocPuzzle::PartTwo => part_2(input),
+    }
+}
+
+fn parse(input: &str) -> Vec<&str> {
+    input.lines().collect()
+}
+
+fn part_1(input: &str) -> Result<u64, SolveError> {
+    let _lines = parse(input);
+    Err(SolveError::Unimplemented)
+}
+
+fn part_2(input: &str) -> Result<u64, SolveError> {
+    let _lines = parse(input);
+    Err(SolveError::Unimplemented)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{part_1, part_2};
+
+    #[test]
+    #[ignore = "not solved yet"]
+    fn test_part_1() {
+        assert_eq!(part_1(include_str!("../examples/{day}/example.txt")), Ok(0));
+    }
+
+    #[test]
+    #[ignore = "not solved yet"]
+    fn test_part_2() {
+        assert_eq!(part_2(include_str!("../examples/{day}/example.txt")), Ok(0));
+    }
+}
+"#;
+
+#[derive(Debug)]
+pub enum ScaffoldError {
+    InvalidDay(String),
+    AlreadyExists(String),
+    NoRegistry(PathBuf),
+    Io(PathBuf, io::Error),
+}
+
+impl fmt::Display for ScaffoldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScaffoldError::InvalidDay(day) => {
+                write!(f, "expected a day from day1 to day25, got `{day}`")
+            }
+            ScaffoldError::AlreadyExists(day) => write!(f, "{day} already exists"),
+            ScaffoldError::NoRegistry(path) => {
+                write!(
+                    f,
+                    "no day modules or solver arms found in {}",
+                    path.display()
+                )
+            }
+            ScaffoldError::Io(path, e) => write!(f, "{}: {e}", path.display()),
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<String, ScaffoldError> {
+    fs::read_to_string(path).map_err(|e| ScaffoldError::Io(path.to_path_buf(), e))
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), ScaffoldError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| ScaffoldError::Io(dir.to_path_buf(), e))?;
+    }
+    fs::write(path, contents).map_err(|e| ScaffoldError::Io(path.to_path_buf(), e))
+}
+
+// Inserts `line` right after the last line starting with `prefix`
+fn insert_after_last(lines: &mut Vec<String>, prefix: &str, line: String) -> bool {
+    match lines
+        .iter()
+        .rposition(|l| l.trim_start().starts_with(prefix))
+    {
+        Some(i) => {
+            lines.insert(i + 1, line);
+            true
+        }
+        None => false,
+    }
+}
+
+// Adds `pub mod <day>;` and its `solver` arm to lib.rs, next to the other days
+fn register(lib: &str, day: &str) -> Option<String> {
+    let mut lines: Vec<String> = lib.lines().map(|l| l.to_string()).collect();
+    let registered = insert_after_last(&mut lines, "pub mod day", format!("pub mod {day};"))
+        && insert_after_last(
+            &mut lines,
+            "\"day",
+            format!("        \"{day}\" => Some({day}::solve),"),
+        );
+    registered.then(|| lines.join("\n") + "\n")
+}
+
+// Creates the module, an empty input and an empty example for `day`, then
+// registers the module. Input and example files that are already there are kept.
+pub fn new_day(root: &Path, day: &str) -> Result<Vec<PathBuf>, ScaffoldError> {
+    day.strip_prefix("day")
+        .and_then(|n| n.parse::<u32>().ok())
+        .filter(|n| (1..=25).contains(n))
+        .ok_or_else(|| ScaffoldError::InvalidDay(day.to_string()))?;
+
+    let lib_path = root.join("src/lib.rs");
+    let lib = read(&lib_path)?;
+    let module_path = root.join(format!("src/{day}.rs"));
+    if module_path.exists() || lib.contains(&format!("mod {day};")) {
+        return Err(ScaffoldError::AlreadyExists(day.to_string()));
+    }
+    let lib = register(&lib, day).ok_or_else(|| ScaffoldError::NoRegistry(lib_path.clone()))?;
+
+    let mut created = vec![];
+    let examples = root.join("examples").join(day);
+    for (path, contents) in [
+        (module_path, TEMPLATE.replace("{day}", day)),
+        (puzzle::input_path(root, day), String::new()),
+        (examples.join("example.txt"), String::new()),
+        (examples.join("example.answers"), String::new()),
+    ] {
+        if !path.exists() {
+            write(&path, &contents)?;
+            created.push(path);
+        }
+    }
+    write(&lib_path, &lib)?;
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{new_day, register, ScaffoldError};
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("aoc-scaffold-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("src/lib.rs"),
+            "pub mod day5;\n\npub fn solver(day: &str) {\n    match day {\n        \"day5\" => Some(day5::solve),\n        _ => None,\n    }\n}\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/day5.rs"), "").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_register() {
+        let lib = "pub mod day1;\npub mod fuzz;\n\nfn solver() {\n    match day {\n        \"day1\" => Some(|input, puzzle| Ok(day1::solve(input, puzzle).into())),\n        _ => None,\n    }\n}\n";
+        assert_eq!(
+            register(lib, "day2").unwrap(),
+            "pub mod day1;\npub mod day2;\npub mod fuzz;\n\nfn solver() {\n    match day {\n        \"day1\" => Some(|input, puzzle| Ok(day1::solve(input, puzzle).into())),\n        \"day2\" => Some(day2::solve),\n        _ => None,\n    }\n}\n"
+        );
+        assert_eq!(register("mod foo;\n", "day2"), None);
+    }
+
+    #[test]
+    fn test_new_day() {
+        let root = temp_root("new");
+        fs::write(root.join("puzzle_6_1"), "already downloaded").unwrap();
+
+        let created = new_day(&root, "day6").unwrap();
+        assert_eq!(
+            created,
+            vec![
+                root.join("src/day6.rs"),
+                root.join("examples/day6/example.txt"),
+                root.join("examples/day6/example.answers"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("puzzle_6_1")).unwrap(),
+            "already downloaded"
+        );
+        let lib = fs::read_to_string(root.join("src/lib.rs")).unwrap();
+        assert!(lib.contains("pub mod day5;\npub mod day6;\n"));
+        assert!(lib.contains("\"day6\" => Some(day6::solve),"));
+        assert!(fs::read_to_string(root.join("src/day6.rs"))
+            .unwrap()
+            .contains("include_str!(\"../examples/day6/example.txt\")"));
+
+        // Nothing is overwritten
+        assert!(matches!(
+            new_day(&root, "day6"),
+            Err(ScaffoldError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            new_day(&root, "day5"),
+            Err(ScaffoldError::AlreadyExists(_))
+        ));
+        assert_eq!(fs::read_to_string(root.join("src/lib.rs")).unwrap(), lib);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_days() {
+        let root = temp_root("invalid");
+        for day in ["6", "day0", "day26", "dayx", "day"] {
+            assert!(matches!(
+                new_day(&root, day),
+                Err(ScaffoldError::InvalidDay(_))
+            ));
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
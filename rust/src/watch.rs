@@ -0,0 +1,516 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::examples;
+use crate::puzzle::{self, AocPuzzle};
+use crate::runner::{self, Run, Status};
+
+// Editors often save in several steps, this lets them finish
+const SETTLE: Duration = Duration::from_millis(50);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::{c_char, c_int, c_short, c_ulong, CString};
+    use std::fs::File;
+    use std::io::{self, Read};
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    const IN_NONBLOCK: c_int = 0o4000;
+    const IN_CLOEXEC: c_int = 0o2000000;
+    const IN_MODIFY: u32 = 0x2;
+    const IN_CLOSE_WRITE: u32 = 0x8;
+    const IN_MOVED_TO: u32 = 0x80;
+    const IN_CREATE: u32 = 0x100;
+    const IN_DELETE: u32 = 0x200;
+    const POLLIN: c_short = 0x1;
+
+    #[repr(C)]
+    struct PollFd {
+        fd: c_int,
+        events: c_short,
+        revents: c_short,
+    }
+
+    extern "C" {
+        fn inotify_init1(flags: c_int) -> c_int;
+        fn inotify_add_watch(fd: c_int, pathname: *const c_char, mask: u32) -> c_int;
+        fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: c_int) -> c_int;
+    }
+
+    pub struct Watcher {
+        file: File,
+        // Watch descriptors and the directories they watch
+        dirs: Vec<(c_int, PathBuf)>,
+    }
+
+    impl Watcher {
+        pub fn new(dirs: &[PathBuf]) -> io::Result<Watcher> {
+            let fd = unsafe { inotify_init1(IN_NONBLOCK | IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // Closes the descriptor when dropped, including on the errors below
+            let file = unsafe { File::from_raw_fd(fd) };
+            let mut watcher = Watcher { file, dirs: vec![] };
+            for dir in dirs {
+                watcher.add(dir)?;
+            }
+            Ok(watcher)
+        }
+
+        pub fn add(&mut self, dir: &Path) -> io::Result<()> {
+            let path = CString::new(dir.as_os_str().as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let mask = IN_MODIFY | IN_CLOSE_WRITE | IN_MOVED_TO | IN_CREATE | IN_DELETE;
+            let wd = unsafe { inotify_add_watch(self.file.as_raw_fd(), path.as_ptr(), mask) };
+            if wd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            self.dirs.push((wd, dir.to_path_buf()));
+            Ok(())
+        }
+
+        pub fn is_watching(&self, dir: &Path) -> bool {
+            self.dirs.iter().any(|(_, d)| d == dir)
+        }
+
+        // Blocks until there are events to read, or for at most `timeout`.
+        // Returns whether there are.
+        pub fn wait(&self, timeout: Option<Duration>) -> io::Result<bool> {
+            let mut fds = PollFd {
+                fd: self.file.as_raw_fd(),
+                events: POLLIN,
+                revents: 0,
+            };
+            let timeout = timeout.map_or(-1, |t| t.as_millis().min(c_int::MAX as u128) as c_int);
+            loop {
+                let ready = unsafe { poll(&mut fds, 1, timeout) };
+                if ready >= 0 {
+                    return Ok(ready > 0);
+                }
+                let error = io::Error::last_os_error();
+                if error.kind() != io::ErrorKind::Interrupted {
+                    return Err(error);
+                }
+            }
+        }
+
+        // Paths of the files touched since the last call, without blocking
+        pub fn changes(&mut self) -> io::Result<Vec<PathBuf>> {
+            let mut buffer = [0u8; 4096];
+            let mut paths = vec![];
+            loop {
+                match self.file.read(&mut buffer) {
+                    Ok(n) => {
+                        for (wd, name) in super::events(&buffer[..n]) {
+                            if let Some((_, dir)) = self.dirs.iter().find(|(w, _)| *w == wd) {
+                                paths.push(dir.join(name));
+                            }
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(paths),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+}
+
+// Watch descriptors and names out of a buffer of `struct inotify_event`:
+// wd (4 bytes), mask (4), cookie (4), len (4), then a NUL padded name of `len` bytes
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn events(mut buffer: &[u8]) -> Vec<(i32, String)> {
+    let mut events = vec![];
+    while buffer.len() >= 16 {
+        let wd = i32::from_ne_bytes(buffer[0..4].try_into().unwrap());
+        let len = u32::from_ne_bytes(buffer[12..16].try_into().unwrap()) as usize;
+        let name = &buffer[16..(16 + len).min(buffer.len())];
+        let name = name.split(|b| *b == 0).next().unwrap_or_default();
+        if !name.is_empty() {
+            events.push((wd, String::from_utf8_lossy(name).to_string()));
+        }
+        buffer = &buffer[(16 + len).min(buffer.len())..];
+    }
+    events
+}
+
+// The input itself, or an example or answers file of the day
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn is_watched(path: &Path, input: &Path, examples_dir: &Path) -> bool {
+    path == input
+        || path.parent() == Some(examples_dir)
+            && path
+                .extension()
+                .is_some_and(|e| e == "txt" || e == "answers")
+}
+
+// `examples/<day>` may not exist yet: its closest existing ancestor is watched
+// instead, so that its creation is noticed
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn closest_existing(dir: &Path) -> Option<&Path> {
+    dir.ancestors().find(|d| d.is_dir())
+}
+
+enum Watch {
+    #[cfg(target_os = "linux")]
+    Inotify(inotify::Watcher),
+    // Modification times of the watched files, compared at every poll
+    Poll(Vec<(PathBuf, Option<SystemTime>)>),
+}
+
+fn watched_files(input: &Path, examples_dir: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut paths = vec![input.to_path_buf()];
+    if let Ok(entries) = fs::read_dir(examples_dir) {
+        paths.extend(entries.filter_map(|e| Some(e.ok()?.path())));
+    }
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|p| {
+            let modified = fs::metadata(&p).and_then(|m| m.modified()).ok();
+            (p, modified)
+        })
+        .collect()
+}
+
+impl Watch {
+    #[cfg(target_os = "linux")]
+    fn new(input: &Path, examples_dir: &Path) -> Watch {
+        let dirs: Vec<PathBuf> = [input.parent(), closest_existing(examples_dir)]
+            .into_iter()
+            .flatten()
+            .filter(|d| d.is_dir())
+            .map(|d| d.to_path_buf())
+            .collect();
+        match inotify::Watcher::new(&dirs) {
+            Ok(watcher) => Watch::Inotify(watcher),
+            Err(e) => {
+                eprintln!("inotify unavailable ({e}), polling instead");
+                Watch::Poll(watched_files(input, examples_dir))
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn new(input: &Path, examples_dir: &Path) -> Watch {
+        Watch::Poll(watched_files(input, examples_dir))
+    }
+
+    // Returns once the input or an example changed, or false if `timeout`
+    // went by first
+    fn wait(&mut self, input: &Path, examples_dir: &Path, timeout: Option<Duration>) -> bool {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let remaining = || deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        match self {
+            #[cfg(target_os = "linux")]
+            Watch::Inotify(watcher) => loop {
+                if !watcher
+                    .wait(remaining())
+                    .expect("Failed to wait for file changes")
+                {
+                    return false;
+                }
+                let paths = watcher.changes().expect("Failed to read file changes");
+                let mut created = false;
+                if let Some(dir) = closest_existing(examples_dir) {
+                    if !watcher.is_watching(dir) {
+                        watcher.add(dir).expect("Failed to watch the examples");
+                        created = dir == examples_dir;
+                    }
+                }
+                if created || paths.iter().any(|p| is_watched(p, input, examples_dir)) {
+                    thread::sleep(SETTLE);
+                    watcher.changes().expect("Failed to read file changes");
+                    return true;
+                }
+            },
+            Watch::Poll(previous) => loop {
+                if remaining().is_some_and(|r| r.is_zero()) {
+                    return false;
+                }
+                thread::sleep(remaining().map_or(POLL_INTERVAL, |r| r.min(POLL_INTERVAL)));
+                let current = watched_files(input, examples_dir);
+                if current != *previous {
+                    thread::sleep(SETTLE);
+                    *previous = watched_files(input, examples_dir);
+                    return true;
+                }
+            },
+        }
+    }
+}
+
+struct Line {
+    label: String,
+    run: Run,
+    expected: Option<u64>,
+}
+
+fn parts() -> [AocPuzzle; 2] {
+    [AocPuzzle::PartOne, AocPuzzle::PartTwo]
+}
+
+fn part_name(puzzle: AocPuzzle) -> &'static str {
+    match puzzle {
+        AocPuzzle::PartOne => "part 1",
+        AocPuzzle::PartTwo => "part 2",
+    }
+}
+
+// Examples first, each on the parts it has answers for, then the real input
+fn evaluate(day: &str, input: &Path, root: &Path) -> Vec<Line> {
+    let mut lines = vec![];
+    let failed = |label: String, puzzle: AocPuzzle, message: String| Line {
+        label,
+        run: Run {
+            day: day.to_string(),
+            puzzle,
+            status: Status::Error(message),
+            elapsed: Duration::ZERO,
+        },
+        expected: None,
+    };
+
+    match examples::load(root, day) {
+        Ok(examples) => {
+            for example in examples {
+                let checked: Vec<(AocPuzzle, Option<u64>)> = match example.answers.is_empty() {
+                    true => parts().into_iter().map(|p| (p, None)).collect(),
+                    false => example
+                        .answers
+                        .iter()
+                        .map(|(p, answer)| (*p, Some(*answer)))
+                        .collect(),
+                };
+                for (puzzle, expected) in checked {
+                    lines.push(Line {
+                        label: format!("{} {}", example.name, part_name(puzzle)),
+                        run: runner::run_on(day, puzzle, &example.input),
+                        expected,
+                    });
+                }
+            }
+        }
+        Err(e) => lines.push(failed("examples".to_string(), AocPuzzle::PartOne, e)),
+    }
+
+    for puzzle in parts() {
+        let label = format!("input {}", part_name(puzzle));
+        lines.push(match puzzle::read_input(input) {
+            Ok(text) => Line {
+                label,
+                run: runner::run_on(day, puzzle, &text),
+                expected: None,
+            },
+            Err(e) => failed(label, puzzle, format!("{}: {e}", input.display())),
+        });
+    }
+    lines
+}
+
+fn result(run: &Run) -> String {
+    match &run.status {
+        Status::Ok(answer) => answer.to_string(),
+        Status::Error(message) => format!("failed: {message}"),
+        Status::Unimplemented => "not implemented".to_string(),
+    }
+}
+
+// One line per answer, marked against the expected answer and the previous run
+fn render(lines: &[Line], previous: &HashMap<String, String>) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| {
+            let result = result(&line.run);
+            let check = match (line.expected, &line.run.status) {
+                (Some(expected), Status::Ok(answer)) if *answer == expected => " ✓".to_string(),
+                (Some(expected), _) => format!(" ✗ expected {expected}"),
+                (None, _) => String::new(),
+            };
+            let change = match previous.get(&line.label) {
+                Some(before) if *before != result => format!(" (was {before})"),
+                _ => String::new(),
+            };
+            format!("{}: {result}{check}{change}", line.label)
+        })
+        .collect()
+}
+
+// Runs the examples and the real input of `day`, then again every time one of them changes
+pub fn watch(root: &Path, day: &str) {
+    let input = puzzle::input_path(root, day);
+    let examples_dir = examples::examples_dir(root, day);
+    let mut watch = Watch::new(&input, &examples_dir);
+    let mut previous: HashMap<String, String> = HashMap::new();
+
+    for round in 1.. {
+        let lines = evaluate(day, &input, root);
+        println!("── {day}, run {round} ──");
+        for line in render(&lines, &previous) {
+            println!("{line}");
+        }
+        previous = lines
+            .iter()
+            .map(|l| (l.label.clone(), result(&l.run)))
+            .collect();
+
+        watch.wait(&input, &examples_dir, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use crate::puzzle::AocPuzzle;
+    use crate::runner::{Run, Status};
+
+    use super::{evaluate, events, is_watched, render, Line};
+
+    // So that a missed event fails the test instead of hanging it
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn event(wd: i32, name: &str, len: usize) -> Vec<u8> {
+        let mut bytes = wd.to_ne_bytes().to_vec();
+        bytes.extend([0u8; 8]);
+        bytes.extend((len as u32).to_ne_bytes());
+        let mut padded = name.as_bytes().to_vec();
+        padded.resize(len, 0);
+        bytes.extend(padded);
+        bytes
+    }
+
+    #[test]
+    fn test_events() {
+        let mut buffer = event(1, "puzzle_1_1", 16);
+        buffer.extend(event(1, "", 0));
+        buffer.extend(event(2, "example.txt", 16));
+        assert_eq!(
+            events(&buffer),
+            vec![
+                (1, "puzzle_1_1".to_string()),
+                (2, "example.txt".to_string())
+            ]
+        );
+        // Truncated events don't read past the buffer
+        assert_eq!(events(&buffer[..20]), vec![(1, "puzz".to_string())]);
+    }
+
+    #[test]
+    fn test_is_watched() {
+        let input = Path::new("/aoc/puzzle_3_1");
+        let examples = Path::new("/aoc/examples/day3");
+        let watched = |path| is_watched(Path::new(path), input, examples);
+        assert!(watched("/aoc/puzzle_3_1"));
+        assert!(watched("/aoc/examples/day3/example.txt"));
+        assert!(watched("/aoc/examples/day3/example.answers"));
+        assert!(!watched("/aoc/puzzle_4_1"));
+        assert!(!watched("/aoc/examples/day3/.example.txt.swp"));
+        // Other text files next to the input or in other days' examples
+        assert!(!watched("/aoc/notes.txt"));
+        assert!(!watched("/aoc/examples/day4/example.txt"));
+    }
+
+    #[test]
+    fn test_render() {
+        let line = |label: &str, status, expected| Line {
+            label: label.to_string(),
+            run: Run {
+                day: "day1".to_string(),
+                puzzle: AocPuzzle::PartOne,
+                status,
+                elapsed: Duration::ZERO,
+            },
+            expected,
+        };
+        let lines = [
+            line("example part 1", Status::Ok(142), Some(142)),
+            line("example part 2", Status::Ok(280), Some(281)),
+            line("input part 1", Status::Ok(55447), None),
+            line("input part 2", Status::Unimplemented, None),
+        ];
+        let previous = HashMap::from([
+            ("example part 1".to_string(), "142".to_string()),
+            ("input part 1".to_string(), "55446".to_string()),
+        ]);
+        assert_eq!(
+            render(&lines, &previous),
+            vec![
+                "example part 1: 142 ✓",
+                "example part 2: 280 ✗ expected 281",
+                "input part 1: 55447 (was 55446)",
+                "input part 2: not implemented",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let lines = evaluate("day1", &root.join("no_such_input"), root);
+        let rendered = render(&lines, &HashMap::new());
+        assert_eq!(
+            rendered[..2],
+            ["example1 part 1: 142 ✓", "example2 part 2: 281 ✓"]
+        );
+        assert!(rendered[2].starts_with("input part 1: failed: "));
+        assert!(rendered[3].starts_with("input part 2: failed: "));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_inotify() {
+        use std::fs;
+
+        use super::inotify::Watcher;
+
+        let dir = std::env::temp_dir().join(format!("aoc-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut watcher = Watcher::new(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(watcher.changes().unwrap(), Vec::<String>::new());
+
+        assert!(!watcher.wait(Some(Duration::from_millis(10))).unwrap());
+        fs::write(dir.join("example.txt"), "1abc2").unwrap();
+        assert!(watcher.wait(Some(TIMEOUT)).unwrap());
+        let paths = watcher.changes().unwrap();
+        assert!(
+            paths.iter().all(|p| *p == dir.join("example.txt")),
+            "{paths:?}"
+        );
+        assert!(!paths.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // The examples are picked up once their directory is created
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_examples_created_later() {
+        use std::fs;
+
+        use super::Watch;
+
+        let root = std::env::temp_dir().join(format!("aoc-watch-new-{}", std::process::id()));
+        let (input, examples_dir) = (root.join("puzzle_6_1"), root.join("examples/day6"));
+        fs::create_dir_all(&root).unwrap();
+        let mut watch = Watch::new(&input, &examples_dir);
+        assert!(!watch.wait(&input, &examples_dir, Some(Duration::from_millis(10))));
+
+        fs::create_dir_all(&examples_dir).unwrap();
+        fs::write(examples_dir.join("example.txt"), "1").unwrap();
+        assert!(watch.wait(&input, &examples_dir, Some(TIMEOUT)));
+        fs::write(examples_dir.join("example.answers"), "part1: 11").unwrap();
+        assert!(watch.wait(&input, &examples_dir, Some(TIMEOUT)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}